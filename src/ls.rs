@@ -1,75 +1,370 @@
+mod access;
+mod acl;
+mod audit;
+mod caps;
+mod du;
+mod explain;
+mod filter;
+mod git;
+mod inode_flags;
+mod json;
+mod predicate;
+mod selinux;
+mod stat;
+mod summary;
+mod xattr;
+
 use std::os::unix::fs::PermissionsExt;
 use std::os::unix::fs::MetadataExt;
 
+use std::collections::{HashMap, HashSet};
+
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use chrono::{TimeZone, Offset};
 
 use colored::*;
 
+/// Long options which take a value, given as either `--option=value` or
+/// `--option value`
+const VALUE_OPTIONS: &[&str] = &["--block-size", "--explain", "--ignore", "--include", "--max-size", "--min-size", "--newer-than",
+                                  "--older-than", "--precision", "--regex", "--sort", "--time", "--type", "--where"];
+
 /// Permissions trio struct
 pub struct Permissions {read: bool, write: bool, execute: bool}
 
-/// Render a file size
-fn render_file_size(size: usize, flags: &Flags) -> Result<String, String>
+/// Read the metadata for a path, only following a symbolic link if `follow` is
+/// set and the link is not broken
+fn read_metadata(path: &std::path::Path, follow: bool) -> std::io::Result<std::fs::Metadata>
 {
-    if flags.byte_sizes || !flags.binary_sizes
+    if follow
     {
-        Ok(format!("{}  ", size))
+        if let Ok(meta) = std::fs::metadata(path)
+        {
+            return Ok(meta);
+        }
     }
-    else
-    {
-        // Units are K,M,G,T,P,E,Z,Y (powers of 1024) or KB,MB,... (powers of 1000).
-        let result: String;
-        let val: f64;
-        let unit: &str;
 
-        if size as u64 > 1024u64.pow(6)
+    std::fs::symlink_metadata(path)
+}
+
+/// Collect every value given to a long option which takes a value
+fn option_values(args: &[String], option: &str) -> Vec<String>
+{
+    let mut values = vec![];
+    let prefix = format!("{}=", option);
+
+    for (i, arg) in args.iter().enumerate()
+    {
+        if let Some(value) = arg.strip_prefix(&prefix)
         {
-            val = size as f64 / 1024f64.powi(6);
-            unit = "E";
+            values.push(String::from(value));
         }
-        else if size as u64 > 1024u64.pow(5)
+        else if arg == option
         {
-            val = size as f64 / 1024f64.powi(5);
-            unit = "P";
+            if let Some(value) = args.get(i + 1)
+            {
+                values.push(value.clone());
+            }
         }
-        else if size as u64 > 1024u64.pow(4)
+    }
+
+    values
+}
+
+/// Unit suffixes for powers of 1024
+const BINARY_UNITS: &[&str] = &["", "K", "M", "G", "T", "P", "E", "Z", "Y"];
+/// Unit suffixes for powers of 1000
+const SI_UNITS: &[&str] = &["", "KB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
+/// IEC unit suffixes for powers of 1024
+const IEC_UNITS: &[&str] = &["", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB", "YiB"];
+
+/// Parse a size such as `512`, `10K`, `1.5MB` or `2GiB` into bytes. Single
+/// letter and IEC suffixes are powers of 1024, two letter suffixes are powers
+/// of 1000.
+fn parse_size(size: &str) -> Result<u64, String>
+{
+    let split = size.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(size.len());
+    let (number, suffix) = size.split_at(split);
+
+    // A unit on its own, such as `K`, is one of that unit
+    let value: f64 = match number.parse()
+    {
+        Ok(value) => value,
+        Err(_) if number.is_empty() && !size.is_empty() => 1f64,
+        Err(_) => {return Err(format!("Invalid size '{}'", size));}
+    };
+
+    let suffix = suffix.to_uppercase();
+    let multiplier = if suffix.is_empty() || suffix == "B"
+    {
+        1f64
+    }
+    else if let Some(power) = BINARY_UNITS.iter().position(|unit| *unit == suffix)
+    {
+        1024f64.powi(power as i32)
+    }
+    else if let Some(power) = SI_UNITS.iter().position(|unit| *unit == suffix)
+    {
+        1000f64.powi(power as i32)
+    }
+    else if let Some(power) = IEC_UNITS.iter().position(|unit| unit.to_uppercase() == suffix)
+    {
+        1024f64.powi(power as i32)
+    }
+    else
+    {
+        return Err(format!("Invalid size '{}'", size));
+    };
+
+    let bytes = value * multiplier;
+
+    if bytes >= u64::MAX as f64
+    {
+        return Err(format!("Size '{}' is too large", size));
+    }
+
+    Ok(bytes.round() as u64)
+}
+
+/// Seconds in each unit of a duration
+const DURATION_UNITS: &[(&str, i64)] = &[("s", 1), ("m", 60), ("h", 3600), ("d", 86400), ("w", 604800), ("y", 31536000)];
+
+/// Parse a duration such as `90s`, `2h`, `3d` or `1h30m` into seconds
+fn parse_duration(duration: &str) -> Result<i64, String>
+{
+    let mut seconds = 0i64;
+    let mut rest = duration;
+
+    if rest.is_empty()
+    {
+        return Err(String::from("Invalid duration ''"));
+    }
+
+    while !rest.is_empty()
+    {
+        let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (number, suffix) = rest.split_at(split);
+        let unit_end = suffix.find(|c: char| c.is_ascii_digit()).unwrap_or(suffix.len());
+        let (unit, remaining) = suffix.split_at(unit_end);
+
+        let value: i64 = match number.parse()
         {
-            val = size as f64 / 1024f64.powi(4);
-            unit = "T";
-        }
-        else if size as u64 > 1024u64.pow(3)
+            Ok(value) => value,
+            Err(_) => {return Err(format!("Invalid duration '{}'", duration));}
+        };
+
+        let multiplier = match DURATION_UNITS.iter().find(|(name, _)| *name == unit)
         {
-            val = size as f64 / 1024f64.powi(3);
-            unit = "G";
-        }
-        else if size as u64 > 1024u64.pow(2)
+            Some((_, multiplier)) => *multiplier,
+            None => {return Err(format!("Invalid duration '{}', expected a unit of s, m, h, d, w or y", duration));}
+        };
+
+        seconds = match value.checked_mul(multiplier).and_then(|value| value.checked_add(seconds))
         {
-            val = size as f64 / 1024f64.powi(2);
-            unit = "M";
+            Some(seconds) => seconds,
+            None => {return Err(format!("Duration '{}' is too long", duration));}
+        };
+        rest = remaining;
+    }
+
+    Ok(seconds)
+}
+
+/// Get the thousands separator for the current locale, falling back to a comma
+/// if the locale doesn't group digits
+fn thousands_separator() -> String
+{
+    // Safety: the locale is only set and read from the main thread, and the
+    // pointers returned by localeconv are checked before they are used
+    unsafe
+    {
+        libc::setlocale(libc::LC_NUMERIC, b"\0".as_ptr() as *const libc::c_char);
+        let conv = libc::localeconv();
+
+        if !conv.is_null() && !(*conv).thousands_sep.is_null()
+        {
+            let separator = std::ffi::CStr::from_ptr((*conv).thousands_sep).to_string_lossy().into_owned();
+
+            if !separator.is_empty()
+            {
+                return separator;
+            }
         }
-        else if size as u64 > 1024u64.pow(1)
+    }
+
+    String::from(",")
+}
+
+/// Insert thousands separators into the integer part of a number, if a
+/// separator is given
+fn group_digits(number: String, separator: Option<&str>) -> String
+{
+    let separator = match separator
+    {
+        Some(separator) => separator,
+        None => {return number;}
+    };
+
+    let split = number.find('.').unwrap_or(number.len());
+    let (integer, rest) = number.split_at(split);
+
+    let mut grouped = String::from("");
+    for (i, c) in integer.chars().enumerate()
+    {
+        if i > 0 && (integer.len() - i) % 3 == 0
         {
-            val = size as f64 / 1024f64.powi(1);
-            unit = "K";
+            grouped += separator;
         }
-        else
+        grouped.push(c);
+    }
+
+    grouped + rest
+}
+
+/// Parse the size of the blocks file sizes are shown in, which can't be zero
+fn parse_block_size(block_size: &str) -> Result<u64, String>
+{
+    match parse_size(block_size)
+    {
+        Ok(0) | Err(_) => Err(format!("Invalid block size '{}'", block_size)),
+        Ok(unit) => Ok(unit)
+    }
+}
+
+/// Parse the number of decimal places for file sizes with prefixes, which
+/// defaults to 1
+fn parse_precision(precision: Option<&str>) -> Result<usize, String>
+{
+    match precision.map(|precision| precision.parse())
+    {
+        None => Ok(1),
+        Some(Ok(places)) if places <= 9 => Ok(places),
+        Some(_) => Err(String::from("Precision must be between 0 and 9"))
+    }
+}
+
+/// Render a file size
+fn render_file_size(size: u64, flags: &Flags) -> Result<String, String>
+{
+    let separator = flags.thousands_separator.as_deref();
+
+    if flags.byte_sizes
+    {
+        return Ok(group_digits(format!("{}", size), separator));
+    }
+
+    // Fixed block sizes are rounded up the same as GNU ls
+    if let (Some(block_size), Some(unit)) = (&flags.block_size, flags.block_bytes)
+    {
+        let suffix: String = block_size.chars().skip_while(|c| c.is_ascii_digit() || *c == '.').collect();
+        let blocks = size.div_ceil(unit);
+
+        return Ok(group_digits(format!("{}", blocks), separator) + &suffix);
+    }
+
+    let (base, units) = if flags.si_sizes
+    {
+        (1000f64, SI_UNITS)
+    }
+    else if flags.iec_sizes
+    {
+        (1024f64, IEC_UNITS)
+    }
+    else if flags.binary_sizes
+    {
+        (1024f64, BINARY_UNITS)
+    }
+    else
+    {
+        return Ok(group_digits(format!("{}", size), separator));
+    };
+
+    let precision = flags.precision;
+    let mut val = size as f64;
+    let mut unit = 0;
+
+    while unit + 1 < units.len()
+    {
+        // Round first, so 1023.99K becomes 1.0M and not 1024.0K
+        let rounded: f64 = format!("{:.1$}", val, precision).parse().unwrap_or(val);
+
+        if rounded < base
         {
-            val = size as f64;
-            unit = "";
+            break;
         }
 
-        if unit == ""
+        val /= base;
+        unit += 1;
+    }
+
+    if unit == 0
+    {
+        Ok(group_digits(format!("{}", size), separator))
+    }
+    else
+    {
+        Ok(group_digits(format!("{:.1$}", val, precision), separator) + units[unit])
+    }
+}
+
+/// Timestamp of an entry which is shown and filtered on
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeField
+{
+    /// Last modification of the contents
+    #[default]
+    Modified,
+    /// Last access
+    Accessed,
+    /// Last change to the contents or the metadata
+    Changed,
+    /// Creation, on filesystems which record it
+    Created
+}
+
+impl TimeField
+{
+    /// Parse the name of a timestamp as given to --time
+    fn parse(name: Option<&str>) -> Result<TimeField, String>
+    {
+        match name
         {
-            result = format!("{}", val as usize);
+            None | Some("mtime") | Some("modified") => Ok(TimeField::Modified),
+            Some("atime") | Some("access") | Some("accessed") | Some("use") => Ok(TimeField::Accessed),
+            Some("ctime") | Some("change") | Some("changed") | Some("status") => Ok(TimeField::Changed),
+            Some("birth") | Some("btime") | Some("created") | Some("creation") => Ok(TimeField::Created),
+            Some(name) => Err(format!("Unknown time '{}', expected mtime, atime, ctime or birth", name))
         }
-        else
+    }
+
+    /// Get the timestamp of an entry in seconds since the epoch, entries
+    /// without a creation time fall back to the modification time
+    fn of(self, metadata: &std::fs::Metadata) -> i64
+    {
+        match self
         {
-            result = format!("{}{}", ((val * 10.0) as usize) as f64 / 10.0, unit);
+            TimeField::Modified => metadata.mtime(),
+            TimeField::Accessed => metadata.atime(),
+            TimeField::Changed => metadata.ctime(),
+            TimeField::Created => match metadata.created().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            {
+                Some(since_epoch) => since_epoch.as_secs() as i64,
+                None => metadata.mtime()
+            }
         }
+    }
 
-        Ok(result)
+    /// Column header in the long view
+    fn header(self) -> &'static str
+    {
+        match self
+        {
+            TimeField::Modified => "Modified",
+            TimeField::Accessed => "Accessed",
+            TimeField::Changed => "Changed",
+            TimeField::Created => "Created"
+        }
     }
 }
 
@@ -94,11 +389,83 @@ fn render_date(dt: i64) -> Result<String, String>
     }
 }
 
+/// Render how long ago a time in seconds since the epoch was
+fn render_relative_date(dt: i64) -> String
+{
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let elapsed = (now - dt).max(0);
+
+    let (count, unit) = if elapsed < 60
+    {
+        (elapsed, "second")
+    }
+    else if elapsed < 60 * 60
+    {
+        (elapsed / 60, "minute")
+    }
+    else if elapsed < 60 * 60 * 24
+    {
+        (elapsed / (60 * 60), "hour")
+    }
+    else if elapsed < 60 * 60 * 24 * 7
+    {
+        (elapsed / (60 * 60 * 24), "day")
+    }
+    else if elapsed < 60 * 60 * 24 * 30
+    {
+        (elapsed / (60 * 60 * 24 * 7), "week")
+    }
+    else if elapsed < 60 * 60 * 24 * 365
+    {
+        (elapsed / (60 * 60 * 24 * 30), "month")
+    }
+    else
+    {
+        (elapsed / (60 * 60 * 24 * 365), "year")
+    };
+
+    if count == 1
+    {
+        format!("{} {} ago", count, unit)
+    }
+    else
+    {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+/// Count the files, directories and hidden entries inside of a directory
+fn count_children(path: &std::path::Path) -> ChildCount
+{
+    let mut count = ChildCount::default();
+
+    if let Ok(items) = std::fs::read_dir(path)
+    {
+        for item in items.filter_map(|item| item.ok())
+        {
+            if item.file_name().to_string_lossy().starts_with('.')
+            {
+                count.hidden += 1;
+            }
+            else if item.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false)
+            {
+                count.dirs += 1;
+            }
+            else
+            {
+                count.files += 1;
+            }
+        }
+    }
+
+    count
+}
+
 /// Render the extra information for a file or directory displayed by using the
 /// '-l' or long argument
-fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
+fn render_long(path: String, follow: bool, details: &Details, widths: &Widths, flags: &Flags) -> Result<StringData, String>
 {
-    let metadata = match std::fs::metadata(path.clone())
+    let metadata = match read_metadata(std::path::Path::new(&path), follow)
     {
         Ok(meta) => meta,
         Err(e) => {return Err(format!("{:?}", e));}
@@ -106,6 +473,7 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
 
     let file_size = metadata.len();
     let is_directory = metadata.is_dir();
+    let is_link = metadata.file_type().is_symlink();
     let perm_mode = (metadata.permissions().mode() & 0o777) as u16;
     let user_perms = Permissions{read: (perm_mode & 0b100000000) != 0, 
                                                 write: (perm_mode & 0b010000000) != 0,
@@ -122,7 +490,8 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
     {
         colored_string: String::from(""),
         raw_string: String::from(""),
-        length: 0
+        length: 0,
+        lines: vec![]
     };
 
     // Inode Section
@@ -149,6 +518,12 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
             string_data.raw_string += "d";
             string_data.length += 1;
         }
+        else if is_link
+        {
+            string_data.colored_string += &format!("{}", "l".bright_cyan());
+            string_data.raw_string += "l";
+            string_data.length += 1;
+        }
         else
         {
             string_data.colored_string += ".";
@@ -202,6 +577,56 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
             }
         }
 
+        // ACL and extended attribute markers, taking the place of the first space
+        if acl::has_acl(std::path::Path::new(&path), follow)
+        {
+            string_data.colored_string += &format!("{}", "+".bright_cyan());
+            string_data.raw_string += "+";
+        }
+        else if xattr::has_attributes(std::path::Path::new(&path), follow)
+        {
+            string_data.colored_string += &format!("{}", "@".bright_purple());
+            string_data.raw_string += "@";
+        }
+        else
+        {
+            string_data.colored_string += " ";
+            string_data.raw_string += " ";
+        }
+        string_data.length += 1;
+
+        // Add Spacing
+        string_data.colored_string += " ";
+        string_data.raw_string += " ";
+        string_data.length += 1;
+    }
+
+    // Inode Flags Section
+    if flags.inode_flags
+    {
+        let (colored_flags, raw_flags) = inode_flags::render_flags(inode_flags::read_flags(std::path::Path::new(&path), &metadata));
+
+        // Inode Flags
+        string_data.colored_string += &colored_flags;
+        string_data.raw_string += &raw_flags;
+        string_data.length += inode_flags::WIDTH;
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
+    // Access Section
+    if flags.effective_access
+    {
+        let (colored_access, raw_access) = access::render_access(&access::effective_access(std::path::Path::new(&path)));
+
+        // Access
+        string_data.colored_string += &colored_access;
+        string_data.raw_string += &raw_access;
+        string_data.length += 3;
+
         // Add Spacing
         string_data.colored_string += "  ";
         string_data.raw_string += "  ";
@@ -237,18 +662,52 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
         string_data.length += 2;
     }
 
+    // Link Group Section
+    if flags.link_groups
+    {
+        let group_str = format!("{:1$}", details.link_group_tag(), widths.link_group);
+
+        // Link Group, colored so the members of a group stand out together
+        let colors = [Color::BrightRed, Color::BrightGreen, Color::BrightYellow, Color::BrightBlue, Color::BrightMagenta, Color::BrightCyan];
+        string_data.colored_string += &match details.link_group
+        {
+            Some((group, _)) => format!("{}", group_str.color(colors[(group - 1) % colors.len()])),
+            None => group_str.clone()
+        };
+        string_data.raw_string += &group_str;
+        string_data.length += group_str.len();
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
     // File size
     if !flags.no_size
     {
-        let mut size_str = format!("{:6}", render_file_size(file_size as usize, flags)?);
+        // Child counts need a wider column than sizes
+        let width = if flags.child_count {12} else {6};
+
+        let mut size_str = format!("{:1$}", render_file_size(file_size, flags)?, width);
 
-        let colored = if std::path::Path::new(&(path.clone())).is_file()
+        let colored = if metadata.is_file()
         {
             format!("{}", size_str.bright_green())
         }
+        else if let Some(total) = details.total_size
+        {
+            size_str = format!("{:1$}", render_file_size(total, flags)?, width);
+            format!("{}", size_str.bright_cyan())
+        }
+        else if let (true, Some(count)) = (flags.child_count, &details.child_count)
+        {
+            size_str = format!("{:1$}", format!("{}f {}d {}h", count.files, count.dirs, count.hidden), width);
+            format!("{}", size_str.bright_cyan())
+        }
         else
         {
-            size_str = String::from("-     ");
+            size_str = format!("{:1$}", "-", width);
             size_str.clone()
         };
 
@@ -287,6 +746,35 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
         string_data.length += 2;
     }
 
+    // Sparse Section
+    if flags.sparse
+    {
+        let ratio_str = match du::allocation_ratio(&metadata)
+        {
+            Some(ratio) => format!("{:.1}%", ratio * 100.0),
+            None => String::from("-")
+        };
+
+        let sparse_str = format!("{} {:>7}", if du::is_sparse(&metadata) {"S"} else {" "}, ratio_str);
+
+        // Allocated Ratio
+        if du::is_sparse(&metadata)
+        {
+            string_data.colored_string += &format!("{}", sparse_str.bright_yellow());
+        }
+        else
+        {
+            string_data.colored_string += &sparse_str;
+        }
+        string_data.raw_string += &sparse_str;
+        string_data.length += sparse_str.len();
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
     // User Section
     if !flags.no_user
     {
@@ -309,10 +797,27 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
         string_data.length += 2;
     }
 
+    // Context Section
+    if flags.context
+    {
+        // Like GNU ls, show a question mark for entries without a context
+        let context_str = format!("{:32}", details.context.clone().unwrap_or_else(|| String::from("?")));
+
+        // Context
+        string_data.colored_string += &format!("{}", context_str.bright_purple());
+        string_data.raw_string += &context_str;
+        string_data.length += context_str.len();
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
     // Timestamp Section
     if !flags.no_time
     {
-        let modified_time: i64 = metadata.mtime();
+        let modified_time: i64 = TimeField::parse(flags.time_field.as_deref())?.of(&metadata);
         let date_str = render_date(modified_time)?;
 
         // Timestamp
@@ -326,9 +831,172 @@ fn render_long(path: String, flags: &Flags) -> Result<StringData, String>
         string_data.length += 2;
     }
 
+    // Git Section
+    if flags.git_status
+    {
+        let (colored, raw) = git::render_status(details.git_status);
+
+        // Git Status
+        string_data.colored_string += &colored;
+        string_data.raw_string += &raw;
+        string_data.length += 2;
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
+    // Last Commit Section
+    if flags.last_commit
+    {
+        let (hash, author, date) = match &details.last_commit
+        {
+            Some(commit) => (commit.hash.clone(), commit.author.chars().take(12).collect(), render_relative_date(commit.time)),
+            None => (String::from("-"), String::from("-"), String::from("-"))
+        };
+
+        let commit_str = format!("{:7}  {:12}  {:14}", hash, author, date);
+
+        // Commit
+        string_data.colored_string += &format!("{}  {}  {}",
+                                               format!("{:7}", hash).bright_purple(),
+                                               format!("{:12}", author).bright_yellow(),
+                                               format!("{:14}", date).bright_blue());
+        string_data.raw_string += &commit_str;
+        // Author names are often not ASCII
+        string_data.length += commit_str.chars().count();
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
+    // Capabilities Section
+    if flags.capabilities
+    {
+        let caps_str = format!("{:1$}", details.capabilities.clone().unwrap_or_else(|| String::from("-")), widths.capabilities);
+
+        // Capabilities
+        string_data.colored_string += &match details.capabilities
+        {
+            Some(_) => format!("{}", caps_str.bright_red().bold()),
+            None => caps_str.clone()
+        };
+        string_data.raw_string += &caps_str;
+        string_data.length += caps_str.len();
+
+        // Add Spacing
+        string_data.colored_string += "  ";
+        string_data.raw_string += "  ";
+        string_data.length += 2;
+    }
+
     Ok(string_data)
 }
 
+/// Extra information about a file or directory gathered while walking the
+/// given paths, displayed in the long view
+#[derive(Debug, Default)]
+pub struct Details
+{
+    /// Git status, if the entry is inside of a repository
+    git_status: Option<git2::Status>,
+    /// Last commit to touch the entry, if it is inside of a repository
+    last_commit: Option<git::CommitInfo>,
+    /// Total size of a directory and its contents
+    total_size: Option<u64>,
+    /// Number of entries inside of a directory
+    child_count: Option<ChildCount>,
+    /// Number of the group of hard links to the same file this entry is in,
+    /// along with the number of other entries collapsed into it
+    link_group: Option<(usize, usize)>,
+    /// File capabilities, rendered the same way as getcap
+    capabilities: Option<String>,
+    /// SELinux security context
+    context: Option<String>
+}
+
+impl Details
+{
+    /// Tag of the group of hard links the entry is in, such as `#2`, or `#2+3`
+    /// if other entries were collapsed into it
+    fn link_group_tag(&self) -> String
+    {
+        match self.link_group
+        {
+            Some((group, 0)) => format!("#{}", group),
+            Some((group, collapsed)) => format!("#{}+{}", group, collapsed),
+            None => String::from("")
+        }
+    }
+}
+
+/// Widths of the columns in the long view which are sized to fit the widest
+/// value in the listing
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Widths
+{
+    /// Width of the link group tags, at least as wide as their header
+    link_group: usize,
+    /// Width of the file capabilities, at least as wide as their header
+    capabilities: usize
+}
+
+impl Widths
+{
+    /// Measure the widest values among the details of the listed entries
+    fn measure<'a>(details: impl Iterator<Item = &'a Details>) -> Widths
+    {
+        let mut widths = Widths {link_group: "Group".len(), capabilities: "Capabilities".len()};
+
+        for details in details
+        {
+            widths.link_group = widths.link_group.max(details.link_group_tag().len());
+
+            if let Some(capabilities) = &details.capabilities
+            {
+                widths.capabilities = widths.capabilities.max(capabilities.len());
+            }
+        }
+
+        widths
+    }
+}
+
+/// Number of entries inside of a directory
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ChildCount
+{
+    /// Number of files, not including hidden files
+    files: usize,
+    /// Number of directories, not including hidden directories
+    dirs: usize,
+    /// Number of hidden files and directories
+    hidden: usize
+}
+
+impl ChildCount
+{
+    /// Total number of entries
+    pub fn total(&self) -> usize
+    {
+        self.files + self.dirs + self.hidden
+    }
+}
+
+/// Put the security context in front of an entry outside of the long view,
+/// the same way as GNU ls
+fn prefix_context(string_data: &mut StringData, details: &Details)
+{
+    let context = details.context.clone().unwrap_or_else(|| String::from("?"));
+
+    string_data.colored_string = format!("{} {}", context.bright_purple(), string_data.colored_string);
+    string_data.raw_string = format!("{} {}", context, string_data.raw_string);
+    string_data.length += context.len() + 1;
+}
+
 /// Colored and non colored strings
 #[derive(Debug)]
 pub struct StringData
@@ -338,7 +1006,9 @@ pub struct StringData
     /// Raw string data
     raw_string: String,
     /// String length
-    length: usize
+    length: usize,
+    /// Colored lines displayed underneath the entry
+    lines: Vec<String>
 }
 
 /// Stores the flags and options set by command line arguments to custom_ls
@@ -378,7 +1048,128 @@ pub struct Flags
     /// Don't show the time stamp
     no_time: bool,
     /// Show octal permission data
-    octal_perms: bool
+    octal_perms: bool,
+    /// Follow all symbolic links
+    dereference: bool,
+    /// Follow symbolic links given on the command line
+    dereference_command_line: bool,
+    /// Don't recurse into directories on other filesystems
+    one_file_system: bool,
+    /// Globs of entries to skip
+    ignore_globs: Vec<String>,
+    /// Globs of files to list, all others are skipped
+    include_globs: Vec<String>,
+    /// Regular expressions of files to list, all others are skipped
+    regexes: Vec<String>,
+    /// Hide entries ignored by git
+    git_ignore: bool,
+    /// Dim entries ignored by git instead of hiding them
+    dim_git_ignored: bool,
+    /// Show the git status of each entry
+    git_status: bool,
+    /// Git states of the entries to list
+    git_states: Vec<String>,
+    /// Show the last commit to touch each entry
+    last_commit: bool,
+    /// Show the total size of the contents of directories
+    dir_size: bool,
+    /// Show the space allocated on disk instead of the apparent size in the
+    /// totals of directories
+    allocated_size: bool,
+    /// Show the number of entries inside of directories
+    child_count: bool,
+    /// Key to sort the listing by
+    sort: Option<String>,
+    /// Print a summary after the listing
+    summary: bool,
+    /// Print only the summary
+    summary_only: bool,
+    /// File sizes in powers of 1000
+    si_sizes: bool,
+    /// File sizes with IEC prefixes
+    iec_sizes: bool,
+    /// Show file sizes in multiples of this size
+    block_size: Option<String>,
+    /// Block size in bytes, parsed from `block_size` by `check`
+    block_bytes: Option<u64>,
+    /// Number of decimal places for file sizes with prefixes, as given
+    size_precision: Option<String>,
+    /// Number of decimal places, parsed from `size_precision` by `check`
+    precision: usize,
+    /// Separator between groups of thousands in file sizes
+    thousands_separator: Option<String>,
+    /// Show how much of each file is allocated on disk
+    sparse: bool,
+    /// Show only sparse files
+    sparse_only: bool,
+    /// Tag entries which are hard links to the same file
+    link_groups: bool,
+    /// List only the first entry of each group of hard links
+    collapse_links: bool,
+    /// List the extended attributes of each entry
+    xattr: bool,
+    /// List the ACL entries of each entry
+    acl: bool,
+    /// Show the file capabilities of each entry
+    capabilities: bool,
+    /// Show the SELinux security context of each entry
+    context: bool,
+    /// Show the inode flags of each entry, such as immutable
+    inode_flags: bool,
+    /// Show what the invoking user can do with each entry
+    effective_access: bool,
+    /// Show only entries readable by the invoking user
+    readable: bool,
+    /// Show only entries writable by the invoking user
+    writable: bool,
+    /// Report risky permissions instead of listing the entries
+    audit: bool,
+    /// Report the risky permissions as JSON
+    audit_json: bool,
+    /// Paths to explain the permissions of
+    explain: Vec<String>,
+    /// Print all the details of each path
+    stat: bool,
+    /// Print the details of each path as JSON
+    stat_json: bool,
+    /// Timestamp to show and filter on
+    time_field: Option<String>,
+    /// Show only entries with a timestamp after each of these
+    newer_than: Vec<String>,
+    /// Show only entries with a timestamp before each of these
+    older_than: Vec<String>,
+    /// Show only files of at least this size
+    min_size: Option<String>,
+    /// Show only files of at most this size
+    max_size: Option<String>,
+    /// Kinds of entries to list, such as `f,l`
+    file_types: Vec<String>,
+    /// Expressions entries must satisfy to be listed
+    where_expressions: Vec<String>
+}
+
+impl Flags
+{
+    /// Returns an error if options which can't be used together, or values
+    /// which are invalid, were given. The values used for every entry are
+    /// parsed once here.
+    fn check(&mut self) -> Result<(), String>
+    {
+        self.block_bytes = match &self.block_size
+        {
+            Some(block_size) => Some(parse_block_size(block_size)?),
+            None => None
+        };
+        self.precision = parse_precision(self.size_precision.as_deref())?;
+
+        // Both fill the size column of directories
+        if self.child_count && self.dir_size
+        {
+            return Err(String::from("--count can't be used together with --du"));
+        }
+
+        Ok(())
+    }
 }
 
 /// The mode to run custom_ls in
@@ -390,7 +1181,11 @@ pub enum Mode
     /// Displays the help for custom_ls
     Help,
     /// Displays the version for custom_ls
-    Version
+    Version,
+    /// Explains the permissions of the given paths
+    Explain,
+    /// Displays all the details of the given paths
+    Stat
 }
 
 
@@ -401,48 +1196,97 @@ pub struct File
     /// File Name
     name: String,
     /// File Path as String
-    path_str: String
+    path_str: String,
+    /// Target of the symbolic link, if the file is a link which is not followed
+    link_target: Option<String>,
+    /// Display the file dimmed
+    dimmed: bool,
+    /// Extra information for the long view
+    details: Details
 }
 
 impl File
 {
     /// Renderes file into
-    pub fn render(&self, flags: &Flags) -> Result<StringData, String>
+    pub fn render(&self, widths: &Widths, flags: &Flags) -> Result<StringData, String>
     {   
         let mut string_data = StringData
         {
             colored_string: self.name.clone(),
             raw_string: self.name.clone(),
-            length: self.name.len()
+            length: self.name.len(),
+            lines: vec![]
         };
 
         if self.name.ends_with(".md") || self.name.ends_with(".toml") || self.name == ".gitignore" || self.name == "makefile"
             || self.name == "Makefile"
-            {
-                string_data.colored_string = format!("{}", string_data.colored_string.bright_yellow().underline());
-            }
+        {
+            string_data.colored_string = format!("{}", string_data.colored_string.bright_yellow().underline());
+        }
 
-            if self.name.ends_with(".png") || self.name.ends_with(".bmp") || self.name.ends_with(".jpg")
-                || self.name.ends_with(".jpeg") || self.name.ends_with(".svg")
-            {
-                string_data.colored_string = format!("{}", string_data.colored_string.bright_purple());
-            }
+        if self.name.ends_with(".png") || self.name.ends_with(".bmp") || self.name.ends_with(".jpg")
+            || self.name.ends_with(".jpeg") || self.name.ends_with(".svg")
+        {
+            string_data.colored_string = format!("{}", string_data.colored_string.bright_purple());
+        }
 
-            if std::fs::metadata(&self.path_str).unwrap().permissions().mode() & 0o111 > 0
-            {
-                string_data.colored_string = format!("{}", string_data.colored_string.bright_green());
-                string_data.colored_string += "*";
-                string_data.raw_string += "*";
-                string_data.length += 1;
-            }
+        if self.link_target.is_some()
+        {
+            string_data.colored_string = format!("{}", string_data.colored_string.bright_cyan());
+        }
+        else if std::fs::metadata(&self.path_str).unwrap().permissions().mode() & 0o111 > 0
+        {
+            string_data.colored_string = format!("{}", string_data.colored_string.bright_green());
+            string_data.colored_string += "*";
+            string_data.raw_string += "*";
+            string_data.length += 1;
+        }
+
+        // Binaries with capabilities are as privileged as setuid ones
+        if self.details.capabilities.is_some()
+        {
+            string_data.colored_string = format!("{}", string_data.colored_string.on_red());
+        }
+
+        if self.dimmed
+        {
+            string_data.colored_string = format!("{}", string_data.colored_string.dimmed());
+        }
+
+        let mut indent = 0;
 
         if flags.long
         {
-            let long_data = render_long(self.path_str.clone(), flags)?;
+            let long_data = render_long(self.path_str.clone(), self.link_target.is_none(), &self.details, widths, flags)?;
 
             string_data.colored_string = long_data.colored_string + &string_data.colored_string;
             string_data.raw_string = long_data.raw_string + &string_data.raw_string;
             string_data.length = long_data.length + string_data.length;
+
+            if let Some(target) = &self.link_target
+            {
+                let arrow = format!(" -> {}", target);
+
+                string_data.colored_string += &arrow;
+                string_data.raw_string += &arrow;
+                string_data.length += arrow.len();
+            }
+
+            indent = long_data.length;
+        }
+        else if flags.context
+        {
+            prefix_context(&mut string_data, &self.details);
+        }
+
+        if flags.xattr
+        {
+            string_data.lines = xattr::render_attributes(std::path::Path::new(&self.path_str), self.link_target.is_none(), flags.acl, indent + 2);
+        }
+
+        if flags.acl
+        {
+            string_data.lines.extend(acl::render_acl(std::path::Path::new(&self.path_str), self.link_target.is_none(), indent + 2));
         }
 
         Ok(string_data)
@@ -456,13 +1300,17 @@ pub struct Directory
     /// Directory Name
     name: String,
     /// Directory Path as String
-    path_str: String
+    path_str: String,
+    /// Display the directory dimmed
+    dimmed: bool,
+    /// Extra information for the long view
+    details: Details
 }
 
 impl Directory
 {
     /// Renderes directory into
-    pub fn render(&self, flags: &Flags) -> Result<StringData, String>
+    pub fn render(&self, widths: &Widths, flags: &Flags) -> Result<StringData, String>
     {
         let mut colored_string = format!("{}", self.name.bright_blue().bold());
         let mut raw_string = self.name.clone();
@@ -470,20 +1318,44 @@ impl Directory
         colored_string += "/";
         raw_string += "/";
 
+        if self.dimmed
+        {
+            colored_string = format!("{}", colored_string.dimmed());
+        }
+
         let mut string_data = StringData
         {
             colored_string: colored_string,
             raw_string: raw_string.clone(),
-            length: raw_string.len()
+            length: raw_string.len(),
+            lines: vec![]
         };
 
+        let mut indent = 0;
+
         if flags.long
         {
-            let long_data = render_long(self.path_str.clone(), flags)?;
+            let long_data = render_long(self.path_str.clone(), true, &self.details, widths, flags)?;
 
             string_data.colored_string = long_data.colored_string + &string_data.colored_string;
             string_data.raw_string = long_data.raw_string + &string_data.raw_string;
             string_data.length = long_data.length + string_data.length;
+
+            indent = long_data.length;
+        }
+        else if flags.context
+        {
+            prefix_context(&mut string_data, &self.details);
+        }
+
+        if flags.xattr
+        {
+            string_data.lines = xattr::render_attributes(std::path::Path::new(&self.path_str), true, flags.acl, indent + 2);
+        }
+
+        if flags.acl
+        {
+            string_data.lines.extend(acl::render_acl(std::path::Path::new(&self.path_str), true, indent + 2));
         }
 
         Ok(string_data)
@@ -497,11 +1369,19 @@ pub struct Display
     /// List of files to display
     files: Vec<File>,
     /// List of directories to display
-    directories: Vec<Directory>
+    directories: Vec<Directory>,
+    /// Number of entries skipped by filters in each directory
+    filtered: HashMap<String, usize>
 }
 
 impl Display
 {
+    /// Count an entry skipped by a filter
+    pub fn count_filtered(&mut self, path: &std::path::Path)
+    {
+        *self.filtered.entry(summary::parent_of(path)).or_insert(0) += 1;
+    }
+
     /// Display all file into
     pub fn display(&self, flags: &Flags) -> Result<(), String>
     {   
@@ -510,6 +1390,10 @@ impl Display
         let mut longest_file_name = 0usize;
         let mut rendered_names: Vec<StringData> = vec![];
 
+        let shown_files = self.files.iter().filter(|file| flags.all || !file.name.starts_with("."));
+        let shown_directories = self.directories.iter().filter(|directory| flags.all || !directory.name.starts_with("."));
+        let widths = Widths::measure(shown_files.map(|file| &file.details).chain(shown_directories.map(|directory| &directory.details)));
+
         for file in &self.files
         {
             if file.name.starts_with(".") && !flags.all
@@ -517,7 +1401,7 @@ impl Display
                 continue;
             }
 
-            let rendered = file.render(flags)?;
+            let rendered = file.render(&widths, flags)?;
 
             if rendered.length > longest_file_name
             {
@@ -534,7 +1418,7 @@ impl Display
                 continue;
             }
 
-            let rendered = directory.render(flags)?;
+            let rendered = directory.render(&widths, flags)?;
 
             if rendered.length > longest_file_name
             {
@@ -551,12 +1435,23 @@ impl Display
 
             if flags.inode
             {
-                header += &format!("{}      ", String::from("inode").white().underline());
+                header += &format!("{}      ", String::from("inode").white().underline());
+            }
+
+            if !flags.no_perms
+            {
+                header += &format!("{} ", String::from("Permissions").white().underline());
+            }
+
+            if flags.inode_flags
+            {
+                header += &format!("{}   ", String::from("Attrs").white().underline());
             }
 
-            if !flags.no_perms
+            if flags.effective_access
             {
-                header += &format!("{} ", String::from("Permissions").white().underline());
+                header += &format!("{}", String::from("Can").white().underline());
+                header += "  ";
             }
 
             if flags.octal_perms
@@ -569,9 +1464,21 @@ impl Display
                 header += &format!("{}  ", String::from("Link").white().underline());
             }
 
+            if flags.link_groups
+            {
+                header += &format!("{}{:width$}  ", String::from("Group").white().underline(), "", width = widths.link_group - "Group".len());
+            }
+
             if !flags.no_size
             {
-                header += &format!("{}    ", String::from("Size").white().underline());
+                if flags.child_count
+                {
+                    header += &format!("{}          ", String::from("Size").white().underline());
+                }
+                else
+                {
+                    header += &format!("{}    ", String::from("Size").white().underline());
+                }
             }
 
             if flags.blocks
@@ -579,14 +1486,42 @@ impl Display
                 header += &format!("{}  ", String::from("Blocks").white().underline());
             }
 
+            if flags.sparse
+            {
+                header += &format!("{}  ", String::from("Allocated").white().underline());
+            }
+
             if !flags.no_user
             {
                 header += &format!("{}      ", String::from("User").white().underline());
             }
 
+            if flags.context
+            {
+                header += &format!("{}{:27}", String::from("Context").white().underline(), "");
+            }
+
             if !flags.no_time
             {
-                header += &format!("{}       ", String::from("Modified").white().underline());
+                let time_header = TimeField::parse(flags.time_field.as_deref())?.header();
+                header += &format!("{}{:width$}", String::from(time_header).white().underline(), "", width = 15 - time_header.len());
+            }
+
+            if flags.git_status
+            {
+                header += &format!("{} ", String::from("Git").white().underline());
+            }
+
+            if flags.last_commit
+            {
+                header += &format!("{}   {}        {}       ", String::from("Commit").white().underline(),
+                                                            String::from("Author").white().underline(),
+                                                            String::from("Committed").white().underline());
+            }
+
+            if flags.capabilities
+            {
+                header += &format!("{}{:width$}  ", String::from("Capabilities").white().underline(), "", width = widths.capabilities - "Capabilities".len());
             }
 
             header += &format!("{}", String::from("Name").white().underline());
@@ -594,7 +1529,8 @@ impl Display
             println!("{}", header);
         }
 
-        let num_per_line = max_line_length / longest_file_name;
+        // Filters can leave nothing to show
+        let num_per_line = max_line_length / longest_file_name.max(1);
         let mut current_line = 0usize;
 
         for rendered in &rendered_names
@@ -611,6 +1547,11 @@ impl Display
             if flags.one_per_line
             {
                 println!("");
+
+                for line in &rendered.lines
+                {
+                    println!("{}", line);
+                }
             }
             else
             {
@@ -645,7 +1586,17 @@ pub struct Utility
     /// Mode for custom_ls to be run in
     mode: Mode,
     /// Display object
-    display: Display
+    display: Display,
+    /// Device and inode pairs of the directories currently being recursed into
+    visited: HashSet<(u64, u64)>,
+    /// Device of the command line directory currently being listed
+    root_device: Option<u64>,
+    /// Filters applied to the discovered entries
+    filters: filter::Filters,
+    /// Git repositories enclosing the discovered entries
+    git: git::Repositories,
+    /// Sizes of the directories walked for their totals
+    sizes: du::Sizes
 }
 
 impl Utility
@@ -655,9 +1606,11 @@ impl Utility
     {
         let mut new_args: Vec<String> = vec![];
 
-        for arg in &arguments
+        for (i, arg) in arguments.iter().enumerate()
         {
-            if !arg.starts_with("--") && arg.starts_with("-") && arg.len() > 2
+            let is_value = i > 0 && VALUE_OPTIONS.contains(&arguments[i - 1].as_str());
+
+            if !is_value && !arg.starts_with("--") && arg.starts_with("-") && arg.len() > 2
             {
                 for c in arg.chars()
                 {
@@ -681,24 +1634,91 @@ impl Utility
             recursive: new_args.contains(&String::from("-R")) || new_args.contains(&String::from("--recursive")),
             all: new_args.contains(&String::from("-a")) || new_args.contains(&String::from("--all")),
             long: new_args.contains(&String::from("-l")) || new_args.contains(&String::from("--long")),
-            one_per_line: new_args.contains(&String::from("-l")) || new_args.contains(&String::from("-1")),
+            one_per_line: new_args.contains(&String::from("-l")) || new_args.contains(&String::from("-1")) || new_args.contains(&String::from("--xattr"))
+                          || new_args.contains(&String::from("--acl")),
             only_dirs: new_args.contains(&String::from("-D")) || new_args.contains(&String::from("--only-dirs")),
             binary_sizes: new_args.contains(&String::from("-b")) || new_args.contains(&String::from("--binary")),
             byte_sizes: new_args.contains(&String::from("-B")) || new_args.contains(&String::from("--bytes")),
             headers: new_args.contains(&String::from("-h")) || new_args.contains(&String::from("--header")),
-            show_links: new_args.contains(&String::from("-H")) || new_args.contains(&String::from("--links")),
+            show_links: new_args.contains(&String::from("-n")) || new_args.contains(&String::from("--links")),
             inode: new_args.contains(&String::from("-i")) || new_args.contains(&String::from("--inode")),
             blocks: new_args.contains(&String::from("-S")) || new_args.contains(&String::from("--blocks")),
             no_perms: new_args.contains(&String::from("--no-permissions")),
             no_size: new_args.contains(&String::from("--no-filesize")),
             no_user: new_args.contains(&String::from("--no-user")),
             no_time: new_args.contains(&String::from("--no-time")),
-            octal_perms: new_args.contains(&String::from("-O")) || new_args.contains(&String::from("--octal"))
+            octal_perms: new_args.contains(&String::from("-O")) || new_args.contains(&String::from("--octal")),
+            dereference: new_args.contains(&String::from("-L")) || new_args.contains(&String::from("--dereference")),
+            dereference_command_line: new_args.contains(&String::from("-H")) || new_args.contains(&String::from("--dereference-command-line")),
+            one_file_system: new_args.contains(&String::from("-x")) || new_args.contains(&String::from("--one-file-system")),
+            ignore_globs: option_values(&new_args, "--ignore"),
+            include_globs: option_values(&new_args, "--include"),
+            regexes: option_values(&new_args, "--regex"),
+            git_ignore: new_args.contains(&String::from("--git-ignore")) || new_args.contains(&String::from("--git-ignore=dim")),
+            dim_git_ignored: new_args.contains(&String::from("--git-ignore=dim")),
+            git_status: new_args.contains(&String::from("--git")),
+            git_states: new_args.iter()
+                                .filter_map(|arg| arg.strip_prefix("--git="))
+                                .flat_map(|states| states.split(','))
+                                .map(String::from)
+                                .collect(),
+            last_commit: new_args.contains(&String::from("--last-commit")),
+            dir_size: new_args.iter().any(|arg| arg == "--du" || arg == "--dir-size" || arg.starts_with("--du=") || arg.starts_with("--dir-size=")),
+            allocated_size: new_args.contains(&String::from("--du=allocated")) || new_args.contains(&String::from("--dir-size=allocated")),
+            child_count: new_args.contains(&String::from("--count")),
+            sort: option_values(&new_args, "--sort").pop(),
+            summary: new_args.contains(&String::from("--summary")),
+            summary_only: new_args.contains(&String::from("--summary-only")),
+            si_sizes: new_args.contains(&String::from("--si")),
+            iec_sizes: new_args.contains(&String::from("--iec")),
+            block_size: option_values(&new_args, "--block-size").pop(),
+            block_bytes: None,
+            size_precision: option_values(&new_args, "--precision").pop(),
+            precision: 1,
+            thousands_separator: if new_args.contains(&String::from("--thousands")) {Some(thousands_separator())} else {None},
+            sparse: new_args.contains(&String::from("--sparse")),
+            sparse_only: new_args.contains(&String::from("--sparse-only")),
+            link_groups: new_args.contains(&String::from("--link-groups")) || new_args.contains(&String::from("--link-groups=collapse")),
+            collapse_links: new_args.contains(&String::from("--link-groups=collapse")),
+            xattr: new_args.contains(&String::from("--xattr")),
+            acl: new_args.contains(&String::from("--acl")),
+            capabilities: new_args.contains(&String::from("--caps")) || new_args.contains(&String::from("--capabilities")),
+            context: new_args.contains(&String::from("-Z")) || new_args.contains(&String::from("--context")),
+            inode_flags: new_args.contains(&String::from("--attrs")),
+            effective_access: new_args.contains(&String::from("--access")),
+            readable: new_args.contains(&String::from("--readable")),
+            writable: new_args.contains(&String::from("--writable")),
+            audit: new_args.contains(&String::from("--audit")) || new_args.contains(&String::from("--audit=json")),
+            audit_json: new_args.contains(&String::from("--audit=json")),
+            explain: option_values(&new_args, "--explain"),
+            stat: new_args.contains(&String::from("--stat")) || new_args.contains(&String::from("--stat=json")),
+            stat_json: new_args.contains(&String::from("--stat=json")),
+            time_field: option_values(&new_args, "--time").pop(),
+            newer_than: option_values(&new_args, "--newer-than"),
+            older_than: option_values(&new_args, "--older-than"),
+            min_size: option_values(&new_args, "--min-size").pop(),
+            max_size: option_values(&new_args, "--max-size").pop(),
+            file_types: option_values(&new_args, "--type"),
+            where_expressions: option_values(&new_args, "--where")
         };
 
         let mut in_files = false;
+        let mut skip_value = false;
         for arg in &new_args[1..]
         {
+            // Skip over the values of options given as `--option value`
+            if skip_value
+            {
+                skip_value = false;
+                continue;
+            }
+
+            if !in_files && VALUE_OPTIONS.contains(&arg.as_str())
+            {
+                skip_value = true;
+                continue;
+            }
+
             if !arg.starts_with("-")
             {
                 in_files = true;
@@ -724,6 +1744,14 @@ impl Utility
             {
                 Mode::Version
             }
+            else if !flags.explain.is_empty()
+            {
+                Mode::Explain
+            }
+            else if flags.stat
+            {
+                Mode::Stat
+            }
             else
             {
                 Mode::List
@@ -737,8 +1765,14 @@ impl Utility
             display: Display
             {
                 files: vec![],
-                directories: vec![]
-            }
+                directories: vec![],
+                filtered: HashMap::new()
+            },
+            visited: HashSet::new(),
+            root_device: None,
+            filters: filter::Filters::default(),
+            git: git::Repositories::default(),
+            sizes: du::Sizes::default()
         }
     }
 
@@ -758,6 +1792,14 @@ impl Utility
             Mode::Version =>
             {
                 self._version()
+            },
+            Mode::Explain =>
+            {
+                self._explain()
+            },
+            Mode::Stat =>
+            {
+                stat::print_stats(&self.flags)
             }
         }
     }
@@ -768,32 +1810,37 @@ impl Utility
         let mut files_to_handle: Vec<String> = vec![];
         let mut dirs_to_handle: Vec<String> = vec![];
 
+        self.flags.check()?;
+        self.filters = filter::Filters::new(&self.flags)?;
+
+        let follow = self.flags.dereference || self.flags.dereference_command_line;
+        // Like ls, links given on the command line are only shown as links
+        // in the long format, otherwise a link to a directory lists its entries
+        let follow_dirs = follow || !self.flags.long;
+
         for file_path in &self.flags.files
         {
             let path = std::path::Path::new(&file_path);
 
-            if !path.exists()
+            let metadata = match read_metadata(path, follow_dirs)
             {
-                return Err(format!("Path '{}' does not exist", file_path));
-            }
+                Ok(meta) => meta,
+                Err(_) => {return Err(format!("Path '{}' does not exist", file_path));}
+            };
 
-            if path.is_dir()
+            if metadata.is_dir()
             {
                 dirs_to_handle.push(file_path.clone());
             }
-            else if path.is_file()
-            {
-                files_to_handle.push(file_path.clone());
-            }
             else
             {
-                return Err(String::from("The file path given is not a directory and not a file, what the hell?!"));
+                files_to_handle.push(file_path.clone());
             }
         }
 
         for file in files_to_handle
         {
-            match self._handle_file(&std::path::Path::new(&file))
+            match self._handle_file(&std::path::Path::new(&file), follow)
                 {
                     Ok(_) => {},
                     Err(e) => {return Err(e);}
@@ -802,6 +1849,12 @@ impl Utility
 
         for dir in dirs_to_handle
         {
+            self.root_device = match std::fs::metadata(&dir)
+            {
+                Ok(meta) => Some(meta.dev()),
+                Err(e) => {return Err(format!("{:?}", e));}
+            };
+
             match self._handle_dir(&std::path::Path::new(&dir))
             {
                 Ok(_) => {},
@@ -809,7 +1862,52 @@ impl Utility
             }
         }
 
-        self.display.display(&self.flags)?;
+        if self.flags.link_groups
+        {
+            self._group_hard_links()?;
+        }
+
+        match self.flags.sort.as_deref()
+        {
+            None => {},
+            Some("children") =>
+            {
+                // Directories with the most entries first
+                self.display.directories.sort_by_key(|dir| std::cmp::Reverse(dir.details.child_count.map(|count| count.total())));
+            },
+            Some(key) => {return Err(format!("Unknown sort key '{}'", key));}
+        }
+
+        if self.flags.audit
+        {
+            return audit::print_audit(&self.display, &self.flags);
+        }
+
+        if !self.flags.summary_only
+        {
+            self.display.display(&self.flags)?;
+        }
+
+        if self.flags.summary || self.flags.summary_only
+        {
+            summary::print_summary(&self.display, &self.flags)?;
+        }
+
+        Ok(())
+    }
+
+    /// Explains the permissions of the paths given to --explain
+    pub fn _explain(&self) -> Result<(), String>
+    {
+        for (i, path) in self.flags.explain.iter().enumerate()
+        {
+            if i > 0
+            {
+                println!();
+            }
+
+            explain::print_explanation(path)?;
+        }
 
         Ok(())
     }
@@ -817,15 +1915,67 @@ impl Utility
     /// Handle Directory
     pub fn _handle_dir(&mut self, path: &std::path::Path) -> Result<(), String>
     {
+        // Refuse to enter a directory which is already being listed further up,
+        // which can only happen when following a symbolic link to an ancestor
+        let key = match std::fs::metadata(path)
+        {
+            Ok(meta) => (meta.dev(), meta.ino()),
+            Err(e) => {return Err(format!("{:?}", e));}
+        };
+
+        if !self.visited.insert(key)
+        {
+            eprintln!("custom_ls: '{}': not listing already-listed directory (symbolic link loop)", path.display());
+            return Ok(());
+        }
+
         self._display_dir(&path)?;
-        let items = std::fs::read_dir(path).unwrap();
 
+        let items = match std::fs::read_dir(path)
+        {
+            Ok(items) => items,
+            Err(e) =>
+            {
+                eprintln!("custom_ls: cannot open directory '{}': {}", path.display(), e);
+                self.visited.remove(&key);
+                return Ok(());
+            }
+        };
+
+        // An entry which can't be read is reported and skipped, the rest of
+        // the directory is still listed
         for path_entity in items
         {
-            let path = path_entity.unwrap().path();
-            if path.is_dir()
+            let path = match path_entity
             {
-                if self.flags.recursive
+                Ok(entry) => entry.path(),
+                Err(e) =>
+                {
+                    eprintln!("custom_ls: cannot read an entry of '{}': {}", path.display(), e);
+                    continue;
+                }
+            };
+            let metadata = match read_metadata(&path, self.flags.dereference)
+            {
+                Ok(meta) => meta,
+                Err(e) =>
+                {
+                    eprintln!("custom_ls: cannot access '{}': {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            if metadata.is_dir()
+            {
+                if !self._include(&path, &metadata)
+                {
+                    self.display.count_filtered(&path);
+                    continue;
+                }
+
+                let other_filesystem = self.flags.one_file_system && Some(metadata.dev()) != self.root_device;
+
+                if self.flags.recursive && !other_filesystem
                 {
                     self._handle_dir(&path)?;
                 }
@@ -834,28 +1984,64 @@ impl Utility
                     self._display_dir(&path)?;
                 }
             }
-            else if path.is_file()
-            {
-                self._handle_file(&path)?;
-            }
             else
             {
-                return Err(String::from("The file path given is not a directory and not a file, what the hell?!"));
+                self._handle_file(&path, self.flags.dereference)?;
             }
         }
 
+        self.visited.remove(&key);
+
         Ok(())
     }
 
     // Handle File
-    pub fn _handle_file(&mut self, path: &std::path::Path) -> Result<(), String>
+    pub fn _handle_file(&mut self, path: &std::path::Path, follow: bool) -> Result<(), String>
     {
-        if !self.flags.only_dirs
+        let metadata = match read_metadata(path, follow)
         {
+            Ok(meta) => meta,
+            Err(e) => {return Err(format!("{:?}", e));}
+        };
+
+        if self.flags.only_dirs || !self._include(path, &metadata) || !self._git_matches(path, false)
+            || !self.filters.matches_access(path) || !self.filters.matches_time(&metadata)
+        {
+            self.display.count_filtered(path);
+        }
+        else
+        {
+            let git_ignored = self._git_ignored(path, false);
+
+            // Symbolic links are shown as links unless they are followed to a target
+            let is_link = match std::fs::symlink_metadata(path)
+            {
+                Ok(meta) => meta.file_type().is_symlink(),
+                Err(e) => {return Err(format!("{:?}", e));}
+            };
+
+            let link_target = if is_link && !(follow && path.exists())
+            {
+                match std::fs::read_link(path)
+                {
+                    Ok(target) => Some(target.to_string_lossy().into_owned()),
+                    Err(e) => {return Err(format!("{:?}", e));}
+                }
+            }
+            else
+            {
+                None
+            };
+
+            let details = self._details(path, false, link_target.is_none());
+
             self.display.files.push(File 
                 {
                     name: String::from(path.file_name().unwrap().to_str().unwrap()),
-                    path_str: String::from(path.as_os_str().to_str().unwrap())
+                    path_str: String::from(path.as_os_str().to_str().unwrap()),
+                    link_target,
+                    dimmed: git_ignored,
+                    details
                 });
         }
         Ok(())
@@ -864,6 +2050,23 @@ impl Utility
     /// Display Directory dat
     fn _display_dir(&mut self, path: &std::path::Path) -> Result<(), String>
     {
+        // Directories in the wrong git state, without the required access,
+        // outside of the time range, of types not being listed, not matching
+        // the expressions or hidden by --sparse-only are still recursed into,
+        // as they may contain entries which match
+        let metadata_matches = match std::fs::metadata(path)
+        {
+            Ok(meta) => self.filters.matches_time(&meta) && self.filters.matches_type(&meta) && self.filters.matches_where(path, &meta)
+                        && self.filters.matches_sparse(&meta),
+            Err(e) => {return Err(format!("{:?}", e));}
+        };
+
+        if !self._git_matches(path, true) || !self.filters.matches_access(path) || !metadata_matches
+        {
+            self.display.count_filtered(path);
+            return Ok(());
+        }
+
         let mut path_str = String::from(path.to_str().unwrap());
 
         if path_str.ends_with("/")
@@ -878,39 +2081,232 @@ impl Utility
             final_str = String::from(val);
         }
 
+        // Directories given on the command line are listed even when ignored
+        // entries are hidden, so they are only dimmed in dim mode
+        let dimmed = self.flags.dim_git_ignored && self._git_ignored(path, true);
+        let details = self._details(path, true, true);
+
         self.display.directories.push(
             Directory
             {
                 name: final_str,
-                path_str: String::from(path.as_os_str().to_str().unwrap())
+                path_str: String::from(path.as_os_str().to_str().unwrap()),
+                dimmed,
+                details
             });
 
         Ok(())
     }
 
+    /// Number the groups of files in the listing which are hard links to the
+    /// same file, collapsing each group into its first entry if requested
+    fn _group_hard_links(&mut self) -> Result<(), String>
+    {
+        let mut groups: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+        let mut order: Vec<(u64, u64)> = vec![];
+
+        for (i, file) in self.display.files.iter().enumerate()
+        {
+            let metadata = match read_metadata(std::path::Path::new(&file.path_str), file.link_target.is_none())
+            {
+                Ok(meta) => meta,
+                Err(e) => {return Err(format!("{:?}", e));}
+            };
+
+            if metadata.nlink() > 1
+            {
+                let key = (metadata.dev(), metadata.ino());
+
+                if !groups.contains_key(&key)
+                {
+                    order.push(key);
+                }
+                groups.entry(key).or_default().push(i);
+            }
+        }
+
+        let mut collapsed: Vec<usize> = vec![];
+        let mut group = 0;
+
+        for key in order
+        {
+            let members = &groups[&key];
+
+            // A file whose other links are outside of the listing isn't a group
+            if members.len() < 2
+            {
+                continue;
+            }
+
+            group += 1;
+
+            if self.flags.collapse_links
+            {
+                self.display.files[members[0]].details.link_group = Some((group, members.len() - 1));
+                collapsed.extend_from_slice(&members[1..]);
+            }
+            else
+            {
+                for member in members
+                {
+                    self.display.files[*member].details.link_group = Some((group, 0));
+                }
+            }
+        }
+
+        collapsed.sort_unstable();
+
+        for i in collapsed.into_iter().rev()
+        {
+            let file = self.display.files.remove(i);
+            self.display.count_filtered(std::path::Path::new(&file.path_str));
+        }
+
+        Ok(())
+    }
+
+    /// Gather the extra information about an entry needed to display it
+    fn _details(&mut self, path: &std::path::Path, is_dir: bool, follow: bool) -> Details
+    {
+        let mut details = Details::default();
+
+        // Capabilities are read even without the long view to highlight names
+        if self.flags.capabilities && !is_dir
+        {
+            details.capabilities = caps::read_capabilities(path, follow);
+        }
+
+        if self.flags.context
+        {
+            details.context = selinux::read_context(path, follow);
+        }
+
+        if self.flags.long && self.flags.git_status
+        {
+            details.git_status = self.git.status(path, is_dir);
+        }
+
+        if self.flags.long && self.flags.last_commit
+        {
+            details.last_commit = self.git.last_commit(path);
+        }
+
+        if self.flags.long && self.flags.dir_size && is_dir
+        {
+            details.total_size = Some(self.sizes.directory_size(path, self.flags.allocated_size, self.flags.one_file_system));
+        }
+
+        if is_dir && ((self.flags.long && self.flags.child_count) || self.flags.sort.as_deref() == Some("children"))
+        {
+            details.child_count = Some(count_children(path));
+        }
+
+        details
+    }
+
+    /// Returns true if the entry at the given path passes the filters which
+    /// apply to both files and directories
+    fn _include(&mut self, path: &std::path::Path, metadata: &std::fs::Metadata) -> bool
+    {
+        if !self.filters.matches(path, metadata)
+        {
+            return false;
+        }
+
+        // Ignored entries are still listed when they are being dimmed
+        self.flags.dim_git_ignored || !self._git_ignored(path, metadata.is_dir())
+    }
+
+    /// Returns true if the entry at the given path is in one of the git states
+    /// being listed
+    fn _git_matches(&mut self, path: &std::path::Path, is_dir: bool) -> bool
+    {
+        if !self.filters.filters_git()
+        {
+            return true;
+        }
+
+        let status = self.git.status(path, is_dir);
+        self.filters.matches_git(status)
+    }
+
+    /// Returns true if git ignored entries are being hidden or dimmed and the
+    /// entry at the given path is ignored
+    fn _git_ignored(&mut self, path: &std::path::Path, is_dir: bool) -> bool
+    {
+        self.flags.git_ignore && self.git.is_ignored(path, is_dir)
+    }
+
     /// Displays the help for custom_ls
     fn _help(&self) -> Result<(), String>
     {
         println!("Usage: custom_ls [OPTION]... [FILE]...");
         println!("Displays information about the FILEs (Will default to the current directory).");
         println!("");
+        println!("  {:4}{:27}{}", "", "--access", "Show what you can read, write and execute in long view");
         println!("  {:4}{:27}{}", "-a,", "--all", "Includes files and directories starting with '.'");
+        println!("  {:4}{:27}{}", "", "--acl", "List the POSIX ACL entries of each entry");
+        println!("  {:4}{:27}{}", "", "--attrs", "Show inode flags (immutable, append only, ...) in long view");
+        println!("  {:4}{:27}{}", "", "--audit[=json]", "Report risky permissions (as JSON) instead of listing");
         println!("  {:4}{:27}{}", "-b,", "--binary", "Show file sizes with binary prefixes");
         println!("  {:4}{:27}{}", "-B,", "--bytes", "Show files sizes always in bytes");
+        println!("  {:4}{:27}{}", "", "--block-size=SIZE", "Show file sizes in multiples of SIZE, such as K, MB or 4096");
+        println!("  {:4}{:27}{}", "", "--caps", "Show file capabilities in long view and highlight their names");
+        println!("  {:4}{:27}{}", "-Z,", "--context", "Show the SELinux security context of each entry");
+        println!("  {:4}{:27}{}", "", "--count", "Show the number of files, directories and hidden entries");
+        println!("  {:4}{:27}{}", "", "", "inside of directories in long view");
         println!("  {:4}{:27}{}", "-D,", "--only-dirs", "List only directories");
+        println!("  {:4}{:27}{}", "", "--dir-size[=allocated]", "Show the total (or allocated) size of directories, also --du");
+        println!("  {:4}{:27}{}", "", "--explain=PATH", "Explain the permissions of PATH in plain words");
+        println!("  {:4}{:27}{}", "", "--git", "Show the git status of each entry in long view");
+        println!("  {:4}{:27}{}", "", "--git=STATE[,STATE]", "List only entries which are tracked, modified, untracked or ignored");
+        println!("  {:4}{:27}{}", "", "--git-ignore[=dim]", "Hide (or dim) entries ignored by git");
         println!("  {:4}{:27}{}", "-h,", "--headers", "Displays headers on long view");
+        println!("  {:4}{:27}{}", "-H,", "--dereference-command-line", "Follow symbolic links given on the command line");
         println!("  {:4}{:27}{}", "", "--help", "Displays the help page");
-        println!("  {:4}{:27}{}", "-H,", "--links", "Display number of hard links");
+        println!("  {:4}{:27}{}", "", "--iec", "Show file sizes with IEC prefixes (KiB, MiB, ...)");
+        println!("  {:4}{:27}{}", "", "--ignore=GLOB", "Don't list entries matching GLOB");
+        println!("  {:4}{:27}{}", "", "--include=GLOB", "List only files matching GLOB");
+        println!("  {:4}{:27}{}", "", "--last-commit", "Show the last commit to touch each entry in long view");
+        println!("  {:4}{:27}{}", "", "--link-groups[=collapse]", "Tag (or collapse) hard links to the same file in long view");
         println!("  {:4}{:27}{}", "-i,", "--inode", "Display inode");
         println!("  {:4}{:27}{}", "-l,", "--long", "Displays more information about the files");
+        println!("  {:4}{:27}{}", "-L,", "--dereference", "Follow all symbolic links");
+        println!("  {:4}{:27}{}", "", "--max-size=SIZE", "List only files of at most SIZE, such as 10M");
+        println!("  {:4}{:27}{}", "", "--min-size=SIZE", "List only files of at least SIZE");
+        println!("  {:4}{:27}{}", "-n,", "--links", "Display number of hard links");
+        println!("  {:4}{:27}{}", "", "--newer-than=WHEN", "List only entries changed after WHEN, a duration such as 2h or");
+        println!("  {:4}{:27}{}", "", "", "3d ago, a date such as 2024-01-31 12:00 or a file's timestamp");
         println!("  {:4}{:27}{}", "", "--no-filesize", "Don't show filesize");
         println!("  {:4}{:27}{}", "", "--no-permissions", "Don't show permissions");
         println!("  {:4}{:27}{}", "", "--no-time", "Don't show timestamp");
         println!("  {:4}{:27}{}", "", "--no-user", "Don't show user");
         println!("  {:4}{:27}{}", "-O,", "--octal", "Display octal permissions");
+        println!("  {:4}{:27}{}", "", "--older-than=WHEN", "List only entries changed before WHEN, see --newer-than");
+        println!("  {:4}{:27}{}", "", "--precision=N", "Show file sizes with prefixes to N (0 to 9) decimal places");
         println!("  {:4}{:27}{}", "-R,", "--recursive", "Go through subdirectories recursively");
+        println!("  {:4}{:27}{}", "", "--readable", "List only entries you can read");
+        println!("  {:4}{:27}{}", "", "--regex=PATTERN", "List only files matching PATTERN");
         println!("  {:4}{:27}{}", "-S,", "--blocks", "Show number of blocks");
+        println!("  {:4}{:27}{}", "", "--si", "Show file sizes in powers of 1000 (KB, MB, ...)");
+        println!("  {:4}{:27}{}", "", "--sparse", "Show the allocated/apparent size ratio, marking sparse files");
+        println!("  {:4}{:27}{}", "", "--sparse-only", "List only sparse files");
+        println!("  {:4}{:27}{}", "", "--sort=children", "List directories with the most entries first");
+        println!("  {:4}{:27}{}", "", "--stat[=json]", "Print every detail of each path given (as JSON), like stat");
+        println!("  {:4}{:27}{}", "", "--summary", "Print the number and size of the entries listed");
+        println!("  {:4}{:27}{}", "", "--summary-only", "Print only the summary");
+        println!("  {:4}{:27}{}", "", "--thousands", "Group the digits of file sizes by the locale's separator");
+        println!("  {:4}{:27}{}", "", "--time=WORD", "Show and filter on mtime (default), atime, ctime or birth");
+        println!("  {:4}{:27}{}", "", "--type=TYPE[,TYPE]", "List only files (f), directories (d), links (l), pipes (p),");
+        println!("  {:4}{:27}{}", "", "", "sockets (s), block (b) or character (c) devices, or executables (x)");
         println!("  {:4}{:27}{}", "", "--version", "Displays the version page");
+        println!("  {:4}{:27}{}", "", "--where=EXPR", "List only entries matching EXPR, such as 'size > 10M and");
+        println!("  {:4}{:27}{}", "", "", "ext in (log, gz) and mtime < 7d and not name ~ \"^tmp\"'");
+        println!("  {:4}{:27}{}", "", "", "Times compare as points in time, an age such as 7d is that long");
+        println!("  {:4}{:27}{}", "", "", "ago, so mtime < 7d means changed more than 7 days ago");
+        println!("  {:4}{:27}{}", "", "--writable", "List only entries you can write to");
+        println!("  {:4}{:27}{}", "", "--xattr", "List the extended attributes of each entry");
+        println!("  {:4}{:27}{}", "-x,", "--one-file-system", "Don't recurse into directories on other filesystems");
         println!("  {:4}{:27}{}", "-1", "", "Display one file per line");
 
         Ok(())
@@ -923,3 +2319,132 @@ impl Utility
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parse_size_accepts_suffixes()
+    {
+        let cases: &[(&str, u64)] = &[("0", 0),
+                                      ("0K", 0),
+                                      ("512", 512),
+                                      ("512B", 512),
+                                      ("1K", 1024),
+                                      ("1k", 1024),
+                                      ("K", 1024),
+                                      ("1.5M", 1572864),
+                                      ("2G", 2147483648),
+                                      ("10KB", 10000),
+                                      ("3MB", 3000000),
+                                      ("2KiB", 2048),
+                                      ("1gib", 1073741824),
+                                      ("15E", 17293822569102704640)];
+
+        for (size, bytes) in cases
+        {
+            assert_eq!(parse_size(size), Ok(*bytes), "parsing '{}'", size);
+        }
+    }
+
+    #[test]
+    fn parse_size_rejects_invalid_sizes()
+    {
+        let cases = ["", "K1", "1Q", "1.2.3", "-1", "1 K"];
+
+        for size in &cases
+        {
+            assert_eq!(parse_size(size), Err(format!("Invalid size '{}'", size)), "parsing '{}'", size);
+        }
+    }
+
+    #[test]
+    fn parse_size_rejects_overflow()
+    {
+        let cases = ["16E", "1Z", "1YB", "18446744073709551616"];
+
+        for size in &cases
+        {
+            assert_eq!(parse_size(size), Err(format!("Size '{}' is too large", size)), "parsing '{}'", size);
+        }
+    }
+
+    #[test]
+    fn group_digits_inserts_separators()
+    {
+        let cases = [("0", "0"),
+                     ("1", "1"),
+                     ("999", "999"),
+                     ("1000", "1,000"),
+                     ("9999", "9,999"),
+                     ("10000", "10,000"),
+                     ("999999", "999,999"),
+                     ("1000000", "1,000,000"),
+                     ("1234.56", "1,234.56"),
+                     ("999.9", "999.9")];
+
+        for (number, grouped) in &cases
+        {
+            assert_eq!(group_digits(String::from(*number), Some(",")), *grouped, "grouping '{}'", number);
+        }
+    }
+
+    #[test]
+    fn parse_duration_accepts_units()
+    {
+        let cases: &[(&str, i64)] = &[("0s", 0),
+                                      ("0d", 0),
+                                      ("90s", 90),
+                                      ("5m", 300),
+                                      ("2h", 7200),
+                                      ("3d", 259200),
+                                      ("1w", 604800),
+                                      ("1y", 31536000),
+                                      ("1h30m", 5400),
+                                      ("1d12h30m15s", 131415)];
+
+        for (duration, seconds) in cases
+        {
+            assert_eq!(parse_duration(duration), Ok(*seconds), "parsing '{}'", duration);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_durations()
+    {
+        let cases = [("", "Invalid duration ''"),
+                     ("d", "Invalid duration 'd'"),
+                     ("1.5h", "Invalid duration '1.5h', expected a unit of s, m, h, d, w or y"),
+                     ("-1d", "Invalid duration '-1d'"),
+                     ("10", "Invalid duration '10', expected a unit of s, m, h, d, w or y"),
+                     ("3x", "Invalid duration '3x', expected a unit of s, m, h, d, w or y"),
+                     ("2H", "Invalid duration '2H', expected a unit of s, m, h, d, w or y")];
+
+        for (duration, error) in &cases
+        {
+            assert_eq!(parse_duration(duration), Err(String::from(*error)), "parsing '{}'", duration);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow()
+    {
+        let cases = [("300000000000y", "Duration '300000000000y' is too long"),
+                     ("9223372036854775807s1s", "Duration '9223372036854775807s1s' is too long"),
+                     // Too many digits to be a number of seconds at all
+                     ("9223372036854775808s", "Invalid duration '9223372036854775808s'")];
+
+        for (duration, error) in &cases
+        {
+            assert_eq!(parse_duration(duration), Err(String::from(*error)), "parsing '{}'", duration);
+        }
+    }
+
+    #[test]
+    fn group_digits_is_disabled_by_default()
+    {
+        assert_eq!(group_digits(String::from("1234567"), None), "1234567");
+    }
+}
@@ -0,0 +1,65 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use colored::*;
+
+use super::Permissions;
+
+/// Returns true if the invoking user is allowed to access a file in the given
+/// mode, taking ownership, groups, ACLs and root into account. Symbolic links
+/// are always followed, as the permissions of a link itself are meaningless.
+fn can_access(path: &Path, mode: libc::c_int) -> bool
+{
+    let c_path = match CString::new(path.as_os_str().as_bytes())
+    {
+        Ok(string) => string,
+        Err(_) => {return false;}
+    };
+
+    // Safety: the path is a valid null terminated string
+    unsafe {libc::faccessat(libc::AT_FDCWD, c_path.as_ptr(), mode, libc::AT_EACCESS) == 0}
+}
+
+/// What the invoking user can actually do with a file, using the effective
+/// user and group ids the same way as `test -r`
+pub fn effective_access(path: &Path) -> Permissions
+{
+    Permissions
+    {
+        read: can_access(path, libc::R_OK),
+        write: can_access(path, libc::W_OK),
+        execute: can_access(path, libc::X_OK)
+    }
+}
+
+/// Render the effective access, in upper case to tell it apart from the
+/// permission bits. Returns the colored and raw strings.
+pub fn render_access(access: &Permissions) -> (String, String)
+{
+    let letters = [(access.read, "R"), (access.write, "W"), (access.execute, "X")];
+
+    let mut colored_string = String::from("");
+    let mut raw_string = String::from("");
+
+    for (allowed, letter) in &letters
+    {
+        if *allowed
+        {
+            colored_string += &match *letter
+            {
+                "R" => format!("{}", letter.bright_yellow()),
+                "W" => format!("{}", letter.bright_red()),
+                _ => format!("{}", letter.bright_green())
+            };
+            raw_string += letter;
+        }
+        else
+        {
+            colored_string += "-";
+            raw_string += "-";
+        }
+    }
+
+    (colored_string, raw_string)
+}
@@ -0,0 +1,166 @@
+use std::ffi::OsStr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use colored::*;
+
+use super::{read_metadata, xattr};
+
+/// Attribute holding the access ACL of a file
+const ACCESS_ATTRIBUTE: &str = "system.posix_acl_access";
+/// Attribute holding the default ACL of a directory
+const DEFAULT_ATTRIBUTE: &str = "system.posix_acl_default";
+
+/// Version of the ACL attribute format
+const ACL_VERSION: u32 = 2;
+
+/// Tags for each kind of ACL entry
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+
+/// A single entry of an ACL
+#[derive(Debug, Clone, Copy)]
+pub struct Entry
+{
+    /// Kind of entry
+    tag: u16,
+    /// User or group id for named entries
+    id: u32,
+    /// Read, write and execute bits
+    perms: u16
+}
+
+/// Returns true if an extended attribute holds an ACL
+pub fn is_acl_attribute(name: &OsStr) -> bool
+{
+    name == ACCESS_ATTRIBUTE || name == DEFAULT_ATTRIBUTE
+}
+
+/// Read an ACL attribute, returning None if the file doesn't have one
+fn read(path: &Path, name: &str, follow: bool) -> Option<Vec<Entry>>
+{
+    let value = xattr::get(path, OsStr::new(name), follow).ok()?;
+
+    if value.len() < 4 || u32::from_le_bytes([value[0], value[1], value[2], value[3]]) != ACL_VERSION
+    {
+        return None;
+    }
+
+    Some(value[4..].chunks_exact(8).map(|entry| Entry
+    {
+        tag: u16::from_le_bytes([entry[0], entry[1]]),
+        perms: u16::from_le_bytes([entry[2], entry[3]]),
+        id: u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]])
+    }).collect())
+}
+
+/// The minimal ACL of a file without an access ACL attribute, which mirrors
+/// its permission bits. Symbolic links which are not followed have none.
+fn minimal_acl(path: &Path, follow: bool) -> Option<Vec<Entry>>
+{
+    let metadata = read_metadata(path, follow).ok()?;
+
+    if metadata.file_type().is_symlink()
+    {
+        return None;
+    }
+
+    let mode = metadata.permissions().mode();
+
+    Some([(ACL_USER_OBJ, 6), (ACL_GROUP_OBJ, 3), (ACL_OTHER, 0)].iter().map(|(tag, shift)| Entry
+    {
+        tag: *tag,
+        id: 0,
+        perms: ((mode >> shift) & 0o7) as u16
+    }).collect())
+}
+
+/// Returns true if a file has an ACL beyond what its permission bits show
+pub fn has_acl(path: &Path, follow: bool) -> bool
+{
+    // Minimal access ACLs only mirror the mode and are normally not stored
+    let extended_access = match read(path, ACCESS_ATTRIBUTE, follow)
+    {
+        Some(entries) => entries.len() > 3,
+        None => false
+    };
+
+    extended_access || read(path, DEFAULT_ATTRIBUTE, follow).is_some()
+}
+
+/// Render read, write and execute bits like the permissions column
+fn render_perms(perms: u16) -> String
+{
+    format!("{}{}{}",
+            if perms & 4 != 0 {"r"} else {"-"},
+            if perms & 2 != 0 {"w"} else {"-"},
+            if perms & 1 != 0 {"x"} else {"-"})
+}
+
+/// Render the entries of an ACL the same way as getfacl, with each line
+/// prefixed by `prefix`
+fn render_entries(entries: &[Entry], prefix: &str) -> Vec<String>
+{
+    let mask = entries.iter().find(|entry| entry.tag == ACL_MASK).map(|entry| entry.perms);
+
+    entries.iter().map(|entry|
+    {
+        let (kind, name) = match entry.tag
+        {
+            ACL_USER_OBJ => ("user", String::from("")),
+            ACL_USER => ("user", match users::get_user_by_uid(entry.id)
+            {
+                Some(user) => user.name().to_string_lossy().into_owned(),
+                None => entry.id.to_string()
+            }),
+            ACL_GROUP_OBJ => ("group", String::from("")),
+            ACL_GROUP => ("group", match users::get_group_by_gid(entry.id)
+            {
+                Some(group) => group.name().to_string_lossy().into_owned(),
+                None => entry.id.to_string()
+            }),
+            ACL_MASK => ("mask", String::from("")),
+            ACL_OTHER => ("other", String::from("")),
+            _ => ("unknown", String::from(""))
+        };
+
+        let mut line = format!("{}{}:{}:{}", prefix, kind, name.bright_yellow(), render_perms(entry.perms));
+
+        // The mask limits everything except the owner and others
+        if let Some(mask) = mask
+        {
+            let masked = entry.tag == ACL_USER || entry.tag == ACL_GROUP_OBJ || entry.tag == ACL_GROUP;
+
+            if masked && entry.perms & !mask != 0
+            {
+                line += &format!("  {}", format!("#effective:{}", render_perms(entry.perms & mask)).bright_red());
+            }
+        }
+
+        line
+    }).collect()
+}
+
+/// Render a line for each entry of the access and default ACLs of a file,
+/// indented by `indent` spaces
+pub fn render_acl(path: &Path, follow: bool, indent: usize) -> Vec<String>
+{
+    let mut lines = vec![];
+
+    // Like getfacl, files without an access ACL show the one their mode implies
+    if let Some(entries) = read(path, ACCESS_ATTRIBUTE, follow).or_else(|| minimal_acl(path, follow))
+    {
+        lines.extend(render_entries(&entries, ""));
+    }
+
+    if let Some(entries) = read(path, DEFAULT_ATTRIBUTE, follow)
+    {
+        lines.extend(render_entries(&entries, "default:"));
+    }
+
+    lines.into_iter().map(|line| format!("{:indent$}{}", "", line, indent = indent)).collect()
+}
@@ -0,0 +1,264 @@
+use std::io::Read;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use colored::*;
+use users::os::unix::UserExt;
+
+use super::{json, read_metadata, Display, Flags};
+
+/// Names of the private keys generated by ssh-keygen
+const SSH_KEY_NAMES: &[&str] = &["id_rsa", "id_dsa", "id_ecdsa", "id_ecdsa_sk", "id_ed25519", "id_ed25519_sk"];
+/// Extensions of files which usually hold a private key
+const KEY_EXTENSIONS: &[&str] = &["key", "p12", "pfx", "ppk"];
+
+/// Kinds of risky permissions found by the audit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind
+{
+    /// A file anyone can write to
+    WorldWritable,
+    /// A directory anyone can write to, where anyone can also delete or
+    /// replace the entries of others
+    WorldWritableDir,
+    /// A program run with the privileges of its owner
+    Setuid,
+    /// A program run with the privileges of its group
+    Setgid,
+    /// An entry owned by a uid with no user
+    UnknownOwner,
+    /// A configuration file in a home directory which the group can change
+    GroupWritableDotfile,
+    /// A private key anyone can read
+    ReadableKey
+}
+
+impl Kind
+{
+    /// Identifier used in the JSON report
+    fn id(self) -> &'static str
+    {
+        match self
+        {
+            Kind::WorldWritable => "world_writable",
+            Kind::WorldWritableDir => "world_writable_dir",
+            Kind::Setuid => "setuid",
+            Kind::Setgid => "setgid",
+            Kind::UnknownOwner => "unknown_owner",
+            Kind::GroupWritableDotfile => "group_writable_dotfile",
+            Kind::ReadableKey => "readable_private_key"
+        }
+    }
+
+    /// Description shown in the text report
+    fn description(self) -> &'static str
+    {
+        match self
+        {
+            Kind::WorldWritable => "world-writable file",
+            Kind::WorldWritableDir => "world-writable directory without the sticky bit",
+            Kind::Setuid => "setuid binary",
+            Kind::Setgid => "setgid binary",
+            Kind::UnknownOwner => "owned by a nonexistent user",
+            Kind::GroupWritableDotfile => "group-writable dotfile in a home directory",
+            Kind::ReadableKey => "private key readable by others"
+        }
+    }
+
+    /// Findings which give other users a way to take over an account or the
+    /// data in it are high severity
+    fn severity(self) -> &'static str
+    {
+        match self
+        {
+            Kind::WorldWritable | Kind::WorldWritableDir | Kind::Setuid | Kind::ReadableKey => "high",
+            Kind::Setgid | Kind::UnknownOwner | Kind::GroupWritableDotfile => "medium"
+        }
+    }
+}
+
+/// A single risky entry found by the audit
+#[derive(Debug)]
+pub struct Finding
+{
+    /// Path of the entry
+    path: String,
+    /// What is risky about it
+    kind: Kind,
+    /// Permission bits, including the special bits
+    mode: u32,
+    /// Owner of the entry
+    uid: u32
+}
+
+/// Returns true if a file looks like it holds a private key, by its name or
+/// by its contents if the name is ambiguous
+fn is_private_key(path: &Path) -> bool
+{
+    let name = match path.file_name()
+    {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => {return false;}
+    };
+
+    if SSH_KEY_NAMES.contains(&name.as_str())
+    {
+        return true;
+    }
+
+    let extension = match path.extension()
+    {
+        Some(extension) => extension.to_string_lossy().to_lowercase(),
+        None => {return false;}
+    };
+
+    // PEM files are often certificates, which are meant to be public
+    if !KEY_EXTENSIONS.contains(&extension.as_str()) && extension != "pem"
+    {
+        return false;
+    }
+
+    let mut start = vec![0u8; 4096];
+
+    match std::fs::File::open(path).and_then(|mut file| file.read(&mut start))
+    {
+        Ok(read) => String::from_utf8_lossy(&start[..read]).contains("PRIVATE KEY") || extension != "pem",
+        // Go by the name if the contents can't be checked
+        Err(_) => extension != "pem"
+    }
+}
+
+/// Returns true if an entry is a dotfile, or inside of a dot directory, in
+/// the home directory of its owner
+fn is_home_dotfile(path: &Path, uid: u32) -> bool
+{
+    let home = match users::get_user_by_uid(uid)
+    {
+        Some(user) => user.home_dir().to_path_buf(),
+        None => {return false;}
+    };
+
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    match path.strip_prefix(&home)
+    {
+        Ok(relative) => relative.components().any(|component| component.as_os_str().to_string_lossy().starts_with('.')),
+        Err(_) => false
+    }
+}
+
+/// Check a single entry for risky permissions
+fn audit_entry(path: &Path, metadata: &std::fs::Metadata) -> Vec<Finding>
+{
+    let mode = metadata.permissions().mode() & 0o7777;
+    let uid = metadata.uid();
+    let mut kinds = vec![];
+
+    if metadata.is_dir()
+    {
+        if mode & 0o002 != 0 && mode & 0o1000 == 0
+        {
+            kinds.push(Kind::WorldWritableDir);
+        }
+    }
+    else if metadata.is_file()
+    {
+        if mode & 0o002 != 0
+        {
+            kinds.push(Kind::WorldWritable);
+        }
+
+        if mode & 0o4000 != 0
+        {
+            kinds.push(Kind::Setuid);
+        }
+
+        // Without group execute the setgid bit means mandatory locking instead
+        if mode & 0o2000 != 0 && mode & 0o010 != 0
+        {
+            kinds.push(Kind::Setgid);
+        }
+
+        if mode & 0o020 != 0 && is_home_dotfile(path, uid)
+        {
+            kinds.push(Kind::GroupWritableDotfile);
+        }
+
+        if mode & 0o004 != 0 && is_private_key(path)
+        {
+            kinds.push(Kind::ReadableKey);
+        }
+    }
+
+    if users::get_user_by_uid(uid).is_none()
+    {
+        kinds.push(Kind::UnknownOwner);
+    }
+
+    kinds.into_iter().map(|kind| Finding {path: path.to_string_lossy().into_owned(), kind, mode, uid}).collect()
+}
+
+/// Audit every entry found while walking the given paths and print the risky
+/// ones instead of the listing, as text or as JSON
+pub fn print_audit(display: &Display, flags: &Flags) -> Result<(), String>
+{
+    let mut findings: Vec<Finding> = vec![];
+
+    // Hidden entries are audited too, they are where most dotfiles and keys live
+    let files = display.files.iter().map(|file| (&file.path_str, file.link_target.is_none()));
+    let dirs = display.directories.iter().map(|dir| (&dir.path_str, true));
+
+    for (path_str, follow) in files.chain(dirs)
+    {
+        let path = Path::new(path_str);
+
+        // The permissions of a symbolic link itself are never used
+        if !follow
+        {
+            continue;
+        }
+
+        match read_metadata(path, follow)
+        {
+            Ok(meta) => findings.extend(audit_entry(path, &meta)),
+            Err(e) => {return Err(format!("{:?}", e));}
+        }
+    }
+
+    findings.sort_by(|a, b| a.path.cmp(&b.path));
+
+    if flags.audit_json
+    {
+        let entries: Vec<String> = findings.iter().map(|finding|
+        {
+            format!("  {{\"path\": {}, \"finding\": {}, \"severity\": \"{}\", \"mode\": \"{:04o}\", \"uid\": {}}}",
+                    json::string(&finding.path), json::string(finding.kind.id()), finding.kind.severity(), finding.mode, finding.uid)
+        }).collect();
+
+        if entries.is_empty()
+        {
+            println!("[]");
+        }
+        else
+        {
+            println!("[\n{}\n]", entries.join(",\n"));
+        }
+
+        return Ok(());
+    }
+
+    for finding in &findings
+    {
+        let severity = match finding.kind.severity()
+        {
+            "high" => format!("{}", "high  ".bright_red().bold()),
+            severity => format!("{}", format!("{:6}", severity).bright_yellow())
+        };
+
+        println!("{} {}  {}  {}", severity, format!("{:04o}", finding.mode).bright_blue(), finding.path, finding.kind.description());
+    }
+
+    println!("{} findings", findings.len().to_string().bold());
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use super::xattr;
+
+/// Attribute holding the file capabilities of an executable
+const CAPABILITY_ATTRIBUTE: &str = "security.capability";
+
+/// Mask for the revision in the first word of the attribute
+const VFS_CAP_REVISION_MASK: u32 = 0xFF00_0000;
+/// Revision with a single 32 bit set of capabilities
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+/// Revision with 64 bit sets of capabilities
+const VFS_CAP_REVISION_2: u32 = 0x0200_0000;
+/// Revision with 64 bit sets of capabilities and a namespace root id
+const VFS_CAP_REVISION_3: u32 = 0x0300_0000;
+/// Flag set if the permitted capabilities are also effective
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+/// Names of the capabilities, indexed by their number
+const CAPABILITY_NAMES: &[&str] = &[
+    "cap_chown", "cap_dac_override", "cap_dac_read_search", "cap_fowner", "cap_fsetid",
+    "cap_kill", "cap_setgid", "cap_setuid", "cap_setpcap", "cap_linux_immutable",
+    "cap_net_bind_service", "cap_net_broadcast", "cap_net_admin", "cap_net_raw", "cap_ipc_lock",
+    "cap_ipc_owner", "cap_sys_module", "cap_sys_rawio", "cap_sys_chroot", "cap_sys_ptrace",
+    "cap_sys_pacct", "cap_sys_admin", "cap_sys_boot", "cap_sys_nice", "cap_sys_resource",
+    "cap_sys_time", "cap_sys_tty_config", "cap_mknod", "cap_lease", "cap_audit_write",
+    "cap_audit_control", "cap_setfcap", "cap_mac_override", "cap_mac_admin", "cap_syslog",
+    "cap_wake_alarm", "cap_block_suspend", "cap_audit_read", "cap_perfmon", "cap_bpf",
+    "cap_checkpoint_restore"];
+
+/// Read a little endian word from the attribute
+fn word(value: &[u8], index: usize) -> Option<u32>
+{
+    let bytes = value.get(index * 4..index * 4 + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Name of a capability, falling back to its number for ones added after this
+/// list was written
+fn capability_name(number: usize) -> String
+{
+    match CAPABILITY_NAMES.get(number)
+    {
+        Some(name) => String::from(*name),
+        None => format!("cap_{}", number)
+    }
+}
+
+/// Read the capabilities of a file and render them the same way as getcap,
+/// such as `cap_net_admin,cap_net_raw=eip`. Returns None if the file has no
+/// capabilities.
+pub fn read_capabilities(path: &Path, follow: bool) -> Option<String>
+{
+    let value = xattr::get(path, OsStr::new(CAPABILITY_ATTRIBUTE), follow).ok()?;
+    let magic = word(&value, 0)?;
+
+    let (permitted, inheritable) = match magic & VFS_CAP_REVISION_MASK
+    {
+        VFS_CAP_REVISION_1 => (word(&value, 1)? as u64, word(&value, 2)? as u64),
+        VFS_CAP_REVISION_2 | VFS_CAP_REVISION_3 =>
+        {
+            (word(&value, 1)? as u64 | (word(&value, 3)? as u64) << 32,
+             word(&value, 2)? as u64 | (word(&value, 4)? as u64) << 32)
+        },
+        _ => {return None;}
+    };
+    let effective = magic & VFS_CAP_FLAGS_EFFECTIVE != 0;
+
+    // Group the capabilities which have the same flags together
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+
+    for number in 0..64
+    {
+        let bit = 1u64 << number;
+        let mut flags = String::from("");
+
+        if effective && permitted & bit != 0
+        {
+            flags += "e";
+        }
+        if inheritable & bit != 0
+        {
+            flags += "i";
+        }
+        if permitted & bit != 0
+        {
+            flags += "p";
+        }
+
+        if flags.is_empty()
+        {
+            continue;
+        }
+
+        match groups.iter_mut().find(|(other, _)| *other == flags)
+        {
+            Some((_, names)) => names.push(capability_name(number)),
+            None => groups.push((flags, vec![capability_name(number)]))
+        }
+    }
+
+    if groups.is_empty()
+    {
+        return None;
+    }
+
+    let rendered: Vec<String> = groups.iter().map(|(flags, names)| format!("{}={}", names.join(","), flags)).collect();
+    Some(rendered.join(" "))
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+/// Size of a single entry, either its apparent size or the space allocated
+/// for it on disk
+pub fn entry_size(metadata: &std::fs::Metadata, allocated: bool) -> u64
+{
+    if allocated
+    {
+        metadata.blocks() * 512
+    }
+    else
+    {
+        metadata.len()
+    }
+}
+
+/// Totals of the directories walked so far, shared by the whole listing so
+/// that hard links are counted once across all of it and each subtree is only
+/// walked once, even when listing recursively
+#[derive(Debug, Default)]
+pub struct Sizes
+{
+    /// Device and inode pairs of the files with multiple hard links counted
+    seen: Mutex<HashSet<(u64, u64)>>,
+    /// Total size of each directory walked, keyed by its device and inode
+    totals: Mutex<HashMap<(u64, u64), u64>>
+}
+
+impl Sizes
+{
+    /// Compute the total size of a directory and everything inside of it, the
+    /// subdirectories are walked in parallel. Symbolic links are not followed
+    /// and files with multiple hard links are only counted once.
+    pub fn directory_size(&self, path: &Path, allocated: bool, one_file_system: bool) -> u64
+    {
+        let metadata = match std::fs::metadata(path)
+        {
+            Ok(meta) => meta,
+            Err(_) => {return 0;}
+        };
+
+        self.total(path, &metadata, metadata.dev(), allocated, one_file_system)
+    }
+
+    /// Get the total size of a directory, walking it if it hasn't been yet
+    fn total(&self, dir: &Path, metadata: &std::fs::Metadata, device: u64, allocated: bool, one_file_system: bool) -> u64
+    {
+        let key = (metadata.dev(), metadata.ino());
+
+        if let Some(total) = self.totals.lock().unwrap().get(&key)
+        {
+            return *total;
+        }
+
+        let total = entry_size(metadata, allocated) + self.walk(dir, device, allocated, one_file_system);
+        self.totals.lock().unwrap().insert(key, total);
+
+        total
+    }
+
+    /// Sum the sizes of the entries inside of a directory
+    fn walk(&self, dir: &Path, device: u64, allocated: bool, one_file_system: bool) -> u64
+    {
+        let entries: Vec<PathBuf> = match std::fs::read_dir(dir)
+        {
+            Ok(items) => items.filter_map(|item| item.ok()).map(|item| item.path()).collect(),
+            Err(_) => {return 0;}
+        };
+
+        entries.par_iter().map(|path|
+        {
+            let metadata = match std::fs::symlink_metadata(path)
+            {
+                Ok(meta) => meta,
+                Err(_) => {return 0;}
+            };
+
+            if one_file_system && metadata.dev() != device
+            {
+                return 0;
+            }
+
+            if metadata.is_dir()
+            {
+                self.total(path, &metadata, device, allocated, one_file_system)
+            }
+            else if metadata.nlink() > 1 && !self.seen.lock().unwrap().insert((metadata.dev(), metadata.ino()))
+            {
+                0
+            }
+            else
+            {
+                entry_size(&metadata, allocated)
+            }
+        }).sum()
+    }
+}
+
+/// Fraction of the apparent size of a regular file which is allocated on disk
+pub fn allocation_ratio(metadata: &std::fs::Metadata) -> Option<f64>
+{
+    if !metadata.is_file() || metadata.len() == 0
+    {
+        return None;
+    }
+
+    Some(entry_size(metadata, true) as f64 / metadata.len() as f64)
+}
+
+/// Returns true if a regular file is sparse, meaning at least one whole block
+/// of its apparent size isn't allocated on disk
+pub fn is_sparse(metadata: &std::fs::Metadata) -> bool
+{
+    metadata.is_file() && entry_size(metadata, true) + metadata.blksize() <= metadata.len()
+}
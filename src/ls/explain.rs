@@ -0,0 +1,238 @@
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+
+use colored::*;
+
+use super::{access, acl, Permissions};
+
+/// Describe what each of the read, write and execute bits allows for a kind
+/// of entry
+fn describe_bits(bits: &Permissions, is_dir: bool) -> String
+{
+    let mut meanings = vec![];
+
+    if is_dir
+    {
+        if bits.read
+        {
+            meanings.push("list the names of its entries");
+        }
+        if bits.write
+        {
+            meanings.push(if bits.execute {"create, delete and rename entries"} else {"nothing extra (w needs x to change entries)"});
+        }
+        if bits.execute
+        {
+            meanings.push("traverse it to reach entries by name");
+        }
+    }
+    else
+    {
+        if bits.read
+        {
+            meanings.push("read the contents");
+        }
+        if bits.write
+        {
+            meanings.push("change the contents");
+        }
+        if bits.execute
+        {
+            meanings.push(if bits.read {"run it as a program"} else {"run it if it's a binary (scripts also need r)"});
+        }
+    }
+
+    if meanings.is_empty()
+    {
+        String::from("no access")
+    }
+    else
+    {
+        format!("can {}", meanings.join(", "))
+    }
+}
+
+/// Render read, write and execute bits like the permissions column
+fn render_bits(bits: &Permissions) -> String
+{
+    format!("{}{}{}",
+            if bits.read {"r".bright_yellow()} else {"-".normal()},
+            if bits.write {"w".bright_red()} else {"-".normal()},
+            if bits.execute {"x".bright_green()} else {"-".normal()})
+}
+
+/// Render a mode the same way as GNU ls, where the special bits take the
+/// place of the execute bits as s, S, t or T
+pub fn render_symbolic(mode: u32) -> String
+{
+    let mut symbolic = String::from(match mode & libc::S_IFMT
+    {
+        libc::S_IFDIR => "d",
+        libc::S_IFLNK => "l",
+        libc::S_IFIFO => "p",
+        libc::S_IFSOCK => "s",
+        libc::S_IFBLK => "b",
+        libc::S_IFCHR => "c",
+        _ => "-"
+    });
+
+    for (shift, special, letter) in &[(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')]
+    {
+        let bits = bits_at(mode, *shift);
+
+        symbolic.push(if bits.read {'r'} else {'-'});
+        symbolic.push(if bits.write {'w'} else {'-'});
+        symbolic.push(match (mode & special != 0, bits.execute)
+        {
+            (true, true) => *letter,
+            (true, false) => letter.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-'
+        });
+    }
+
+    symbolic
+}
+
+/// Get the read, write and execute bits at the given shift of a mode
+fn bits_at(mode: u32, shift: u32) -> Permissions
+{
+    Permissions
+    {
+        read: mode >> shift & 0o4 != 0,
+        write: mode >> shift & 0o2 != 0,
+        execute: mode >> shift & 0o1 != 0
+    }
+}
+
+/// Describe the setuid, setgid and sticky bits which are set
+fn describe_special_bits(mode: u32, is_dir: bool) -> Vec<String>
+{
+    let mut lines = vec![];
+
+    if mode & 0o4000 != 0
+    {
+        lines.push(if is_dir
+        {
+            format!("{}: ignored on directories", "setuid".bright_red())
+        }
+        else
+        {
+            format!("{}: runs with the privileges of the owner, not of whoever starts it", "setuid".bright_red())
+        });
+    }
+
+    if mode & 0o2000 != 0
+    {
+        lines.push(if is_dir
+        {
+            format!("{}: new entries get the group of this directory instead of the creator's", "setgid".bright_red())
+        }
+        else if mode & 0o010 != 0
+        {
+            format!("{}: runs with the privileges of the group", "setgid".bright_red())
+        }
+        else
+        {
+            format!("{}: without group execute this marks mandatory locking, which Linux no longer supports", "setgid".bright_red())
+        });
+    }
+
+    if mode & 0o1000 != 0
+    {
+        lines.push(if is_dir
+        {
+            format!("{}: only the owner of an entry, or of this directory, can delete or rename it", "sticky".bright_blue())
+        }
+        else
+        {
+            format!("{}: ignored on files", "sticky".bright_blue())
+        });
+    }
+
+    lines
+}
+
+/// Print a breakdown of the permissions of an entry, following symbolic links
+pub fn print_explanation(path_str: &str) -> Result<(), String>
+{
+    let path = Path::new(path_str);
+
+    let link_metadata = match std::fs::symlink_metadata(path)
+    {
+        Ok(meta) => meta,
+        Err(_) => {return Err(format!("Path '{}' does not exist", path_str));}
+    };
+
+    // The permissions of a symbolic link itself are never used
+    if link_metadata.file_type().is_symlink()
+    {
+        let target = std::fs::read_link(path).map(|target| target.to_string_lossy().into_owned()).unwrap_or_default();
+        println!("{} is a symbolic link to {}, its permissions are never used", path_str.bold(), target.bright_cyan());
+    }
+
+    let metadata = match std::fs::metadata(path)
+    {
+        Ok(meta) => meta,
+        Err(_) => {return Err(format!("Path '{}' is a broken symbolic link", path_str));}
+    };
+
+    let is_dir = metadata.is_dir();
+    let mode = metadata.permissions().mode();
+
+    let kind = if is_dir {"directory"} else if metadata.is_file() {"regular file"} else {"special file"};
+    let user = match users::get_user_by_uid(metadata.uid())
+    {
+        Some(user) => user.name().to_string_lossy().into_owned(),
+        None => format!("unknown uid {}", metadata.uid())
+    };
+    let group = match users::get_group_by_gid(metadata.gid())
+    {
+        Some(group) => group.name().to_string_lossy().into_owned(),
+        None => format!("unknown gid {}", metadata.gid())
+    };
+    let has_acl = acl::has_acl(path, true);
+
+    println!("{}: {}", path_str.bold(), kind);
+    println!("  Mode: {}  Octal: {}", render_symbolic(mode).bold(), format!("{:04o}", mode & 0o7777).bright_blue());
+    println!();
+
+    let group_name = if has_acl {format!("{} (ACL mask)", group)} else {group};
+
+    for (class, name, shift) in &[("User", user, 6), ("Group", group_name, 3), ("Other", String::from("everyone else"), 0)]
+    {
+        let bits = bits_at(mode, *shift);
+        println!("  {:6}{}  {}{}", class, render_bits(&bits), format!("{:24}", name).bright_yellow(), describe_bits(&bits, is_dir));
+    }
+
+    let special = describe_special_bits(mode, is_dir);
+
+    if !special.is_empty()
+    {
+        println!();
+
+        for line in special
+        {
+            println!("  {}", line);
+        }
+    }
+
+    if has_acl
+    {
+        println!();
+        println!("  This entry has an ACL, so the group bits above are its mask, the most");
+        println!("  that named users and groups and the owning group can be given:");
+
+        for line in acl::render_acl(path, true, 4)
+        {
+            println!("{}", line);
+        }
+    }
+
+    let effective = access::effective_access(path);
+    let (colored_access, _) = access::render_access(&effective);
+    println!();
+    println!("  You: {}  {}", colored_access, describe_bits(&effective, is_dir));
+
+    Ok(())
+}
@@ -0,0 +1,347 @@
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{parse_duration, parse_size, Flags, TimeField};
+use super::access;
+use super::du;
+use super::git;
+use super::predicate;
+
+use chrono::TimeZone;
+use glob::Pattern;
+use regex::Regex;
+
+/// Formats accepted for the dates given to --newer-than and --older-than
+const DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"];
+
+/// Letters for the kinds of entries given to --type
+const TYPE_LETTERS: &str = "fdlpsbcx";
+
+/// Filters applied to the entries collected while walking the given paths
+#[derive(Debug, Default)]
+pub struct Filters
+{
+    /// Entries matching any of these globs are skipped
+    ignore: Vec<Pattern>,
+    /// If not empty, files must match one of these globs
+    include: Vec<Pattern>,
+    /// If not empty, files must match one of these regular expressions
+    regex: Vec<Regex>,
+    /// If not empty, entries must be in one of these git states
+    git_states: Vec<git::State>,
+    /// Files must be sparse
+    sparse_only: bool,
+    /// Entries must be readable by the invoking user
+    readable: bool,
+    /// Entries must be writable by the invoking user
+    writable: bool,
+    /// Timestamp compared against the time ranges
+    time_field: TimeField,
+    /// Entries must have a timestamp after each of these
+    newer_than: Vec<i64>,
+    /// Entries must have a timestamp before each of these
+    older_than: Vec<i64>,
+    /// Files must be at least this many bytes
+    min_size: Option<u64>,
+    /// Files must be at most this many bytes
+    max_size: Option<u64>,
+    /// If not empty, entries must be one of these kinds
+    types: Vec<char>,
+    /// Entries must satisfy each of these expressions
+    expressions: Vec<predicate::Expr>
+}
+
+impl Filters
+{
+    /// Compile the filters requested by the command line arguments
+    pub fn new(flags: &Flags) -> Result<Filters, String>
+    {
+        let mut filters = Filters
+        {
+            sparse_only: flags.sparse_only,
+            readable: flags.readable,
+            writable: flags.writable,
+            ..Filters::default()
+        };
+
+        for glob in &flags.ignore_globs
+        {
+            filters.ignore.push(compile_glob(glob)?);
+        }
+
+        for glob in &flags.include_globs
+        {
+            filters.include.push(compile_glob(glob)?);
+        }
+
+        for pattern in &flags.regexes
+        {
+            match Regex::new(pattern)
+            {
+                Ok(regex) => filters.regex.push(regex),
+                Err(e) => {return Err(format!("Invalid regex '{}': {}", pattern, e));}
+            }
+        }
+
+        for name in &flags.git_states
+        {
+            filters.git_states.push(git::State::parse(name)?);
+        }
+
+        filters.time_field = TimeField::parse(flags.time_field.as_deref())?;
+
+        for when in &flags.newer_than
+        {
+            filters.newer_than.push(parse_when(when, filters.time_field)?);
+        }
+
+        for when in &flags.older_than
+        {
+            filters.older_than.push(parse_when(when, filters.time_field)?);
+        }
+
+        if let Some(size) = &flags.min_size
+        {
+            filters.min_size = Some(parse_size(size)?);
+        }
+
+        if let Some(size) = &flags.max_size
+        {
+            filters.max_size = Some(parse_size(size)?);
+        }
+
+        for letter in flags.file_types.iter().flat_map(|types| types.split(','))
+        {
+            match letter.chars().next()
+            {
+                Some(c) if letter.len() == 1 && TYPE_LETTERS.contains(c) => filters.types.push(c),
+                _ => {return Err(format!("Unknown type '{}', expected one of f, d, l, p, s, b, c or x", letter));}
+            }
+        }
+
+        for expression in &flags.where_expressions
+        {
+            filters.expressions.push(predicate::parse(expression)?);
+        }
+
+        Ok(filters)
+    }
+
+    /// Returns true if entries are being filtered by their git state
+    pub fn filters_git(&self) -> bool
+    {
+        !self.git_states.is_empty()
+    }
+
+    /// Returns true if an entry with the given git status should be listed,
+    /// entries outside of a repository have no status
+    pub fn matches_git(&self, status: Option<git2::Status>) -> bool
+    {
+        match status
+        {
+            Some(status) => self.git_states.is_empty() || self.git_states.iter().any(|state| state.matches(status)),
+            None => self.git_states.is_empty()
+        }
+    }
+
+    /// Returns true if the invoking user has the access to an entry required
+    /// by the filters
+    pub fn matches_access(&self, path: &std::path::Path) -> bool
+    {
+        if !self.readable && !self.writable
+        {
+            return true;
+        }
+
+        let access = access::effective_access(path);
+        (!self.readable || access.read) && (!self.writable || access.write)
+    }
+
+    /// Returns true if the timestamp of an entry is inside of the time ranges
+    pub fn matches_time(&self, metadata: &std::fs::Metadata) -> bool
+    {
+        if self.newer_than.is_empty() && self.older_than.is_empty()
+        {
+            return true;
+        }
+
+        let time = self.time_field.of(metadata);
+        self.newer_than.iter().all(|limit| time > *limit) && self.older_than.iter().all(|limit| time < *limit)
+    }
+
+    /// Returns true if sparse files aren't being singled out, or the entry is
+    /// one. Directories are never sparse.
+    pub fn matches_sparse(&self, metadata: &std::fs::Metadata) -> bool
+    {
+        !self.sparse_only || du::is_sparse(metadata)
+    }
+
+    /// Returns true if an entry is one of the kinds being listed
+    pub fn matches_type(&self, metadata: &std::fs::Metadata) -> bool
+    {
+        if self.types.is_empty()
+        {
+            return true;
+        }
+
+        self.types.iter().any(|letter| is_type(metadata, *letter))
+    }
+
+    /// Returns true if an entry satisfies the --where expressions
+    pub fn matches_where(&self, path: &std::path::Path, metadata: &std::fs::Metadata) -> bool
+    {
+        self.expressions.iter().all(|expression| expression.matches(path, metadata))
+    }
+
+    /// Returns true if the entry at the given path should be listed
+    pub fn matches(&self, path: &std::path::Path, metadata: &std::fs::Metadata) -> bool
+    {
+        let is_dir = metadata.is_dir();
+
+        let name = match path.file_name()
+        {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => String::from("")
+        };
+        let full_path = path.to_string_lossy().into_owned();
+
+        if self.ignore.iter().any(|glob| glob.matches(&name) || glob.matches(&full_path))
+        {
+            return false;
+        }
+
+        // Directories are not subject to the include filters, otherwise
+        // recursing to find the files inside of them would be impossible
+        if is_dir
+        {
+            return true;
+        }
+
+        if !self.include.is_empty() && !self.include.iter().any(|glob| glob.matches(&name) || glob.matches(&full_path))
+        {
+            return false;
+        }
+
+        if !self.regex.is_empty() && !self.regex.iter().any(|regex| regex.is_match(&name) || regex.is_match(&full_path))
+        {
+            return false;
+        }
+
+        if !self.matches_sparse(metadata)
+        {
+            return false;
+        }
+
+        // The apparent size, the same as the size column and the size field of
+        // --where, even when directory totals are of the allocated size
+        let size = metadata.len();
+
+        if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max)
+        {
+            return false;
+        }
+
+        if !self.matches_type(metadata) || !self.matches_where(path, metadata)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Returns true if an entry is the kind given by one of the letters accepted
+/// by --type, executables are regular files with any execute bit set
+pub fn is_type(metadata: &std::fs::Metadata, letter: char) -> bool
+{
+    let file_type = metadata.file_type();
+
+    match letter
+    {
+        'f' => file_type.is_file(),
+        'd' => file_type.is_dir(),
+        'l' => file_type.is_symlink(),
+        'p' => file_type.is_fifo(),
+        's' => file_type.is_socket(),
+        'b' => file_type.is_block_device(),
+        'c' => file_type.is_char_device(),
+        'x' => file_type.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        _ => false
+    }
+}
+
+/// Compile a single glob pattern
+fn compile_glob(glob: &str) -> Result<Pattern, String>
+{
+    match Pattern::new(glob)
+    {
+        Ok(pattern) => Ok(pattern),
+        Err(e) => Err(format!("Invalid glob '{}': {}", glob, e))
+    }
+}
+
+/// Parse the point in time given to --newer-than or --older-than, which is
+/// either a duration before now, a local date and time, or the timestamp of a
+/// file. Returns the time in seconds since the epoch.
+pub fn parse_when(when: &str, time_field: TimeField) -> Result<i64, String>
+{
+    if let Ok(duration) = parse_duration(when)
+    {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|since_epoch| since_epoch.as_secs() as i64).unwrap_or(0);
+        return Ok(now - duration);
+    }
+
+    let date_time = DATE_TIME_FORMATS.iter()
+                                     .find_map(|format| chrono::NaiveDateTime::parse_from_str(when, format).ok())
+                                     .or_else(|| chrono::NaiveDate::parse_from_str(when, "%Y-%m-%d").ok().map(|date| date.and_hms(0, 0, 0)));
+
+    if let Some(date_time) = date_time
+    {
+        return match chrono::Local.from_local_datetime(&date_time).earliest()
+        {
+            Some(local) => Ok(local.timestamp()),
+            None => Err(format!("Time '{}' doesn't exist in the local time zone", when))
+        };
+    }
+
+    match std::fs::metadata(when)
+    {
+        Ok(meta) => Ok(time_field.of(&meta)),
+        Err(_) => Err(format!("Invalid time '{}', expected a duration such as 2h, a date such as 2024-01-31 or a file", when))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use super::super::Utility;
+
+    /// Filters for the given command line arguments
+    fn filters(args: &[&str]) -> Filters
+    {
+        let mut arguments = vec![String::from("custom_ls")];
+        arguments.extend(args.iter().map(|arg| String::from(*arg)));
+
+        Filters::new(&Utility::new(arguments).flags).unwrap()
+    }
+
+    #[test]
+    fn size_filters_ignore_allocated_totals()
+    {
+        // A sparse file has an apparent size of 1M but next to nothing allocated
+        let path = std::env::temp_dir().join(format!("custom_ls_size_filter_{}", std::process::id()));
+        std::fs::File::create(&path).unwrap().set_len(1 << 20).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+
+        let cases = [("--min-size=1M", true), ("--min-size=2M", false), ("--max-size=512K", false), ("--max-size=1M", true)];
+
+        for (option, expected) in &cases
+        {
+            assert_eq!(filters(&[option]).matches(&path, &metadata), *expected, "filtering with {}", option);
+            assert_eq!(filters(&[option, "--du=allocated"]).matches(&path, &metadata), *expected, "filtering with {} --du=allocated", option);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,576 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use git2::{Commit, ObjectType, Oid, Repository, Status, StatusOptions, Tree};
+
+/// Statuses of the changed entries in a repository
+struct Statuses
+{
+    /// Status of each entry reported by git, directories which are entirely
+    /// ignored are reported as a single entry
+    entries: HashMap<PathBuf, Status>,
+    /// Combined status of the entries inside of each directory
+    directories: HashMap<PathBuf, Status>
+}
+
+/// The last commit to touch an entry
+#[derive(Debug, Clone)]
+pub struct CommitInfo
+{
+    /// Abbreviated commit hash
+    pub hash: String,
+    /// Name of the author of the commit
+    pub author: String,
+    /// Commit time in seconds since the epoch
+    pub time: i64
+}
+
+/// The history of a repository, walked back from HEAD only as far as needed
+/// to find the last commit of the entries in the directories listed so far
+#[derive(Default)]
+struct History
+{
+    /// Commit HEAD points to
+    head: Option<Oid>,
+    /// Commits still to be walked, newest first, along with their commit time
+    /// and the order they were queued in, which breaks ties so commits made in
+    /// the same second are walked before their parents
+    pending: BinaryHeap<(i64, Reverse<usize>, Oid)>,
+    /// Commits which have been added to `pending`, so merges are only walked
+    /// once
+    queued: HashSet<Oid>,
+    /// Last commit to touch each entry seen changing so far, keyed by the
+    /// path relative to the root of the repository
+    commits: HashMap<PathBuf, CommitInfo>,
+    /// Last commit to touch each entry of the directories listed so far,
+    /// keyed by the relative path of the directory
+    directories: HashMap<PathBuf, HashMap<OsString, CommitInfo>>
+}
+
+/// An opened repository
+struct Repo
+{
+    /// Canonical path to the working directory
+    workdir: PathBuf,
+    /// The repository itself
+    repo: Repository,
+    /// Statuses of the repository, read the first time they are needed
+    statuses: Option<Statuses>,
+    /// History of the repository, read the first time it is needed
+    history: Option<History>
+}
+
+/// Git repositories enclosing the listed paths, opened as they are first seen
+#[derive(Default)]
+pub struct Repositories
+{
+    /// Opened repositories
+    repos: Vec<Repo>,
+    /// Index into `repos` of the repository enclosing each directory seen
+    dirs: HashMap<PathBuf, Option<usize>>
+}
+
+impl std::fmt::Debug for Repositories
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        let workdirs: Vec<&PathBuf> = self.repos.iter().map(|repo| &repo.workdir).collect();
+
+        f.debug_struct("Repositories").field("workdirs", &workdirs).finish()
+    }
+}
+
+impl Repositories
+{
+    /// Open the repository enclosing a canonical directory path, returning its
+    /// index into `repos`
+    fn open(&mut self, dir: &Path) -> Option<usize>
+    {
+        let repo = Repository::discover(dir).ok()?;
+        let workdir = std::fs::canonicalize(repo.workdir()?).ok()?;
+
+        if let Some(index) = self.repos.iter().position(|other| other.workdir == workdir)
+        {
+            return Some(index);
+        }
+
+        self.repos.push(Repo {workdir, repo, statuses: None, history: None});
+        Some(self.repos.len() - 1)
+    }
+
+    /// Find the repository enclosing the given path, along with the path
+    /// relative to the working directory of that repository
+    fn lookup(&mut self, path: &Path) -> Option<(&mut Repo, PathBuf)>
+    {
+        let name = path.file_name()?;
+
+        // Only the parent is canonicalized, so symbolic links are not resolved
+        let parent = match path.parent()
+        {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new(".")
+        };
+        let dir = std::fs::canonicalize(parent).ok()?;
+
+        let index = match self.dirs.get(&dir)
+        {
+            Some(index) => *index,
+            None =>
+            {
+                let index = self.open(&dir);
+                self.dirs.insert(dir.clone(), index);
+                index
+            }
+        }?;
+
+        let repo = &mut self.repos[index];
+        let relative = dir.join(name).strip_prefix(&repo.workdir).ok()?.to_path_buf();
+
+        // The repository's own files are not part of the working tree
+        if relative.starts_with(".git")
+        {
+            return None;
+        }
+
+        Some((repo, relative))
+    }
+
+    /// Returns true if the entry at the given path is ignored by git, taking
+    /// into account every `.gitignore`, `.git/info/exclude` and the global
+    /// excludes file
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool
+    {
+        match self.lookup(path)
+        {
+            Some((repo, relative)) =>
+            {
+                let mut relative = relative.to_string_lossy().into_owned();
+
+                // Directory only patterns such as `target/` need the trailing slash
+                if is_dir
+                {
+                    relative.push('/');
+                }
+
+                repo.repo.is_path_ignored(&relative).unwrap_or(false)
+            },
+            None => false
+        }
+    }
+
+    /// Get the git status of the entry at the given path, directories get the
+    /// combined status of everything inside of them. Returns None if the path
+    /// is not inside of a repository.
+    pub fn status(&mut self, path: &Path, is_dir: bool) -> Option<Status>
+    {
+        let (repo, relative) = self.lookup(path)?;
+
+        if repo.statuses.is_none()
+        {
+            repo.statuses = Some(read_statuses(&repo.repo)?);
+        }
+        let statuses = repo.statuses.as_ref()?;
+
+        let mut status = Status::CURRENT;
+
+        // Entries inside of ignored directories share their status
+        for ancestor in relative.ancestors()
+        {
+            if let Some(entry) = statuses.entries.get(ancestor)
+            {
+                status |= *entry;
+            }
+        }
+
+        if is_dir
+        {
+            if let Some(combined) = statuses.directories.get(&relative)
+            {
+                status |= *combined;
+            }
+        }
+
+        Some(status)
+    }
+
+    /// Get the last commit to touch the entry at the given path. Returns None
+    /// if the path is not inside of a repository or has never been committed.
+    pub fn last_commit(&mut self, path: &Path) -> Option<CommitInfo>
+    {
+        let (repo, relative) = self.lookup(path)?;
+
+        let dir = relative.parent()?.to_path_buf();
+        let name = relative.file_name()?.to_os_string();
+
+        if repo.history.is_none()
+        {
+            repo.history = Some(read_history(&repo.repo).unwrap_or_default());
+        }
+        let history = repo.history.as_mut()?;
+
+        if !history.directories.contains_key(&dir)
+        {
+            let commits = history.walk_directory(&repo.repo, &dir);
+            history.directories.insert(dir.clone(), commits);
+        }
+
+        history.directories.get(&dir)?.get(&name).cloned()
+    }
+}
+
+impl History
+{
+    /// Keep walking back through the history until the last commit to touch
+    /// every entry of a directory, as of HEAD, is found, and return them
+    fn walk_directory(&mut self, repo: &Repository, dir: &Path) -> HashMap<OsString, CommitInfo>
+    {
+        let names = self.head.and_then(|head| repo.find_commit(head).ok())
+                             .map(|head| entry_names(repo, &head, dir))
+                             .unwrap_or_default();
+        let mut unresolved: HashSet<PathBuf> = names.iter()
+                                                    .map(|name| dir.join(name))
+                                                    .filter(|path| !self.commits.contains_key(path))
+                                                    .collect();
+
+        while !unresolved.is_empty()
+        {
+            let commit = match self.next_commit(repo)
+            {
+                Some(commit) => commit,
+                None => break
+            };
+
+            for path in self.record(repo, &commit)
+            {
+                unresolved.remove(&path);
+            }
+        }
+
+        names.into_iter()
+             .filter_map(|name| self.commits.get(&dir.join(&name)).cloned().map(|info| (name, info)))
+             .collect()
+    }
+
+    /// Take the newest commit still to be walked, queueing its parents
+    fn next_commit<'r>(&mut self, repo: &'r Repository) -> Option<Commit<'r>>
+    {
+        while let Some((_, _, oid)) = self.pending.pop()
+        {
+            let commit = match repo.find_commit(oid)
+            {
+                Ok(commit) => commit,
+                Err(_) => continue
+            };
+
+            for parent in commit.parents()
+            {
+                if self.queued.insert(parent.id())
+                {
+                    self.pending.push((parent.time().seconds(), Reverse(self.queued.len()), parent.id()));
+                }
+            }
+
+            return Some(commit);
+        }
+
+        None
+    }
+
+    /// Record a commit as the last one to touch every entry it changed which
+    /// has not been seen changing in a newer commit, returning those entries
+    fn record(&mut self, repo: &Repository, commit: &Commit) -> Vec<PathBuf>
+    {
+        let tree = match commit.tree()
+        {
+            Ok(tree) => tree,
+            Err(_) => {return vec![];}
+        };
+        let parents: Vec<Tree> = commit.parents().filter_map(|parent| parent.tree().ok()).collect();
+
+        // Skip commits which leave the whole tree the same as a parent
+        if parents.iter().any(|parent| parent.id() == tree.id())
+        {
+            return vec![];
+        }
+
+        // An entry the same as in any parent was changed by that parent
+        let changed = if parents.is_empty()
+        {
+            changed_entries(repo, &tree, None, Path::new(""))
+        }
+        else
+        {
+            let mut sets = parents.iter().map(|parent| changed_entries(repo, &tree, Some(parent), Path::new("")));
+            let first = sets.next().unwrap_or_default();
+            sets.fold(first, |changed, other| changed.intersection(&other).cloned().collect())
+        };
+
+        if changed.is_empty()
+        {
+            return vec![];
+        }
+
+        let info = CommitInfo
+        {
+            hash: commit.id().to_string()[..7].to_string(),
+            author: String::from(commit.author().name().unwrap_or("unknown")),
+            time: commit.time().seconds()
+        };
+
+        let mut recorded = vec![];
+
+        for path in changed
+        {
+            if !self.commits.contains_key(&path)
+            {
+                self.commits.insert(path.clone(), info.clone());
+                recorded.push(path);
+            }
+        }
+
+        recorded
+    }
+}
+
+/// Read HEAD, which the walk through the history starts from
+fn read_history(repo: &Repository) -> Option<History>
+{
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+
+    Some(History
+    {
+        head: Some(head.id()),
+        pending: vec![(head.time().seconds(), Reverse(0), head.id())].into(),
+        queued: vec![head.id()].into_iter().collect(),
+        ..History::default()
+    })
+}
+
+/// Get the names of the entries in a directory, given relative to the root
+/// of the repository, as of a commit
+fn entry_names(repo: &Repository, commit: &Commit, dir: &Path) -> Vec<OsString>
+{
+    let root = match commit.tree()
+    {
+        Ok(root) => root,
+        Err(_) => {return vec![];}
+    };
+
+    let tree = if dir.as_os_str().is_empty()
+    {
+        root
+    }
+    else
+    {
+        match root.get_path(dir).and_then(|entry| repo.find_tree(entry.id()))
+        {
+            Ok(tree) => tree,
+            Err(_) => {return vec![];}
+        }
+    };
+
+    tree.iter().filter_map(|entry| entry.name().map(OsString::from)).collect()
+}
+
+/// Get the path of every entry, at any depth, of a tree which differs from
+/// the same entry in an older tree. Only subtrees which differ are descended
+/// into.
+fn changed_entries(repo: &Repository, tree: &Tree, old: Option<&Tree>, prefix: &Path) -> HashSet<PathBuf>
+{
+    let mut changed = HashSet::new();
+
+    for entry in tree.iter()
+    {
+        let name = match entry.name()
+        {
+            Some(name) => name,
+            None => continue
+        };
+        let old_entry = old.and_then(|old| old.get_name(name));
+
+        if old_entry.as_ref().map(|old_entry| old_entry.id()) == Some(entry.id())
+        {
+            continue;
+        }
+
+        let path = prefix.join(name);
+
+        if entry.kind() == Some(ObjectType::Tree)
+        {
+            if let Ok(subtree) = repo.find_tree(entry.id())
+            {
+                let old_subtree = old_entry.filter(|old_entry| old_entry.kind() == Some(ObjectType::Tree))
+                                           .and_then(|old_entry| repo.find_tree(old_entry.id()).ok());
+
+                changed.extend(changed_entries(repo, &subtree, old_subtree.as_ref(), &path));
+            }
+        }
+
+        changed.insert(path);
+    }
+
+    changed
+}
+
+/// Read the status of every changed, untracked and ignored entry in a
+/// repository
+fn read_statuses(repo: &Repository) -> Option<Statuses>
+{
+    let mut options = StatusOptions::new();
+    options.include_untracked(true)
+           .include_ignored(true)
+           .recurse_untracked_dirs(true)
+           .recurse_ignored_dirs(false);
+
+    let mut statuses = Statuses
+    {
+        entries: HashMap::new(),
+        directories: HashMap::new()
+    };
+
+    for entry in repo.statuses(Some(&mut options)).ok()?.iter()
+    {
+        let path = match entry.path()
+        {
+            Some(path) => PathBuf::from(path.trim_end_matches('/')),
+            None => continue
+        };
+
+        // Ignored files only count towards their own status, otherwise every
+        // directory containing build output would show as ignored
+        if !entry.status().is_ignored()
+        {
+            for ancestor in path.ancestors().skip(1)
+            {
+                *statuses.directories.entry(ancestor.to_path_buf()).or_insert(Status::CURRENT) |= entry.status();
+            }
+        }
+
+        statuses.entries.insert(path, entry.status());
+    }
+
+    Some(statuses)
+}
+
+/// Character and color for a single column of the status
+fn status_char(conflicted: bool, new: bool, modified: bool, deleted: bool, renamed: bool, typechange: bool, ignored: bool) -> (char, colored::Color)
+{
+    if conflicted
+    {
+        ('U', colored::Color::BrightRed)
+    }
+    else if modified
+    {
+        ('M', colored::Color::BrightBlue)
+    }
+    else if deleted
+    {
+        ('D', colored::Color::Red)
+    }
+    else if renamed
+    {
+        ('R', colored::Color::Yellow)
+    }
+    else if typechange
+    {
+        ('T', colored::Color::Yellow)
+    }
+    else if new
+    {
+        ('N', colored::Color::BrightGreen)
+    }
+    else if ignored
+    {
+        ('I', colored::Color::BrightBlack)
+    }
+    else
+    {
+        ('-', colored::Color::BrightBlack)
+    }
+}
+
+/// Render a git status as two characters, the first for the index (staged
+/// changes) and the second for the working tree. Returns the colored and raw
+/// strings.
+pub fn render_status(status: Option<Status>) -> (String, String)
+{
+    use colored::Colorize;
+
+    let status = match status
+    {
+        Some(status) => status,
+        None => {return (String::from("  "), String::from("  "));}
+    };
+
+    let conflicted = status.is_conflicted();
+
+    let (staged, staged_color) = status_char(conflicted,
+                                             status.is_index_new(),
+                                             status.is_index_modified(),
+                                             status.is_index_deleted(),
+                                             status.is_index_renamed(),
+                                             status.is_index_typechange(),
+                                             false);
+    let (unstaged, unstaged_color) = status_char(conflicted,
+                                                 status.is_wt_new(),
+                                                 status.is_wt_modified(),
+                                                 status.is_wt_deleted(),
+                                                 status.is_wt_renamed(),
+                                                 status.is_wt_typechange(),
+                                                 status.is_ignored());
+
+    (format!("{}{}", staged.to_string().color(staged_color), unstaged.to_string().color(unstaged_color)),
+     format!("{}{}", staged, unstaged))
+}
+
+/// A git state entries can be filtered by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State
+{
+    /// Entries known to git
+    Tracked,
+    /// Entries with staged or unstaged changes
+    Modified,
+    /// Entries not known to git
+    Untracked,
+    /// Entries ignored by git
+    Ignored
+}
+
+impl State
+{
+    /// Parse a state from its name on the command line
+    pub fn parse(name: &str) -> Result<State, String>
+    {
+        match name
+        {
+            "tracked" => Ok(State::Tracked),
+            "modified" => Ok(State::Modified),
+            "untracked" => Ok(State::Untracked),
+            "ignored" => Ok(State::Ignored),
+            _ => Err(format!("Unknown git state '{}', expected one of tracked, modified, untracked or ignored", name))
+        }
+    }
+
+    /// Returns true if an entry with the given status is in this state
+    pub fn matches(self, status: Status) -> bool
+    {
+        let changed = status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED
+                                        | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE
+                                        | Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED
+                                        | Status::WT_TYPECHANGE | Status::CONFLICTED);
+        let untracked = status.is_wt_new() && !status.is_index_new();
+
+        match self
+        {
+            // A directory with changes contains tracked entries even if some of
+            // its other entries are untracked
+            State::Tracked => !status.is_ignored() && (changed || !untracked),
+            State::Modified => changed,
+            State::Untracked => untracked,
+            State::Ignored => status.is_ignored()
+        }
+    }
+}
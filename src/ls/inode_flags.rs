@@ -0,0 +1,84 @@
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use colored::*;
+
+/// Request for reading the inode flags of an open file, `_IOR('f', 1, long)`
+const FS_IOC_GETFLAGS: u64 = (2 << 30) | ((std::mem::size_of::<libc::c_long>() as u64) << 16) | ((b'f' as u64) << 8) | 1;
+
+/// Flags shown in the column, in the order they are displayed along with the
+/// letter chattr uses for each
+const SHOWN_FLAGS: &[(libc::c_int, char)] = &[
+    (0x0000_0010, 'i'), // FS_IMMUTABLE_FL
+    (0x0000_0020, 'a'), // FS_APPEND_FL
+    (0x0000_0040, 'd'), // FS_NODUMP_FL
+    (0x0000_0080, 'A'), // FS_NOATIME_FL
+    (0x0000_0004, 'c'), // FS_COMPR_FL
+    (0x0080_0000, 'C')  // FS_NOCOW_FL
+];
+
+/// Width of the rendered flags
+pub const WIDTH: usize = 6;
+
+/// Read the inode flags of a regular file or directory. Returns None for other
+/// kinds of files, including symbolic links which aren't followed, or if the
+/// filesystem doesn't support inode flags.
+pub fn read_flags(path: &Path, metadata: &std::fs::Metadata) -> Option<libc::c_int>
+{
+    if !metadata.is_file() && !metadata.is_dir()
+    {
+        return None;
+    }
+
+    // Opening without blocking, only the descriptor is needed
+    let file = std::fs::OpenOptions::new().read(true)
+                                          .custom_flags(libc::O_NONBLOCK)
+                                          .open(path)
+                                          .ok()?;
+    let mut flags: libc::c_int = 0;
+
+    // Safety: the descriptor is open and the kernel writes a single int
+    let result = unsafe {libc::ioctl(file.as_raw_fd(), FS_IOC_GETFLAGS as _, &mut flags as *mut libc::c_int)};
+
+    if result < 0
+    {
+        return None;
+    }
+
+    Some(flags)
+}
+
+/// Render the inode flags the same way as lsattr, with a dash in place of each
+/// flag which isn't set. Returns the colored and raw strings.
+pub fn render_flags(flags: Option<libc::c_int>) -> (String, String)
+{
+    let mut colored_string = String::from("");
+    let mut raw_string = String::from("");
+
+    for (flag, letter) in SHOWN_FLAGS
+    {
+        match flags
+        {
+            Some(flags) if flags & flag != 0 =>
+            {
+                let letter = letter.to_string();
+
+                // Immutable and append only files can't be written to regardless of their permissions
+                colored_string += &match letter.as_str()
+                {
+                    "i" | "a" => format!("{}", letter.bright_red().bold()),
+                    _ => format!("{}", letter.bright_yellow())
+                };
+                raw_string += &letter;
+            },
+            _ =>
+            {
+                colored_string += "-";
+                raw_string += "-";
+            }
+        }
+    }
+
+    (colored_string, raw_string)
+}
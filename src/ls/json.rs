@@ -0,0 +1,21 @@
+/// Render a string as a quoted JSON string, escaping quotes, backslashes and
+/// control characters
+pub fn string(value: &str) -> String
+{
+    let mut escaped = String::from("\"");
+
+    for c in value.chars()
+    {
+        match c
+        {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c)
+        }
+    }
+
+    escaped + "\""
+}
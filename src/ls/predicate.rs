@@ -0,0 +1,826 @@
+use std::cmp::Ordering;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+
+use super::filter;
+use super::{parse_duration, parse_size, TimeField};
+
+/// Words with a meaning of their own, which have to be quoted to be used as
+/// values
+const KEYWORDS: &[&str] = &["and", "or", "not", "in"];
+
+/// A token of an expression, along with the range of bytes it came from
+#[derive(Debug, Clone, PartialEq)]
+enum Token
+{
+    /// Unquoted word, such as a field name, keyword, number or size
+    Word(String),
+    /// Quoted string
+    Text(String),
+    /// Comparison operator
+    Operator(Operator),
+    /// Opening parenthesis
+    Open,
+    /// Closing parenthesis
+    Close,
+    /// Comma between the values of a list
+    Comma,
+    /// End of the expression
+    End
+}
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator
+{
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// Matches a regular expression
+    Match,
+    /// Doesn't match a regular expression
+    NotMatch
+}
+
+impl Operator
+{
+    /// Returns true if two values compared in the given order satisfy the
+    /// operator
+    fn accepts(self, ordering: Ordering) -> bool
+    {
+        match self
+        {
+            Operator::Equal | Operator::Match => ordering == Ordering::Equal,
+            Operator::NotEqual | Operator::NotMatch => ordering != Ordering::Equal,
+            Operator::Less => ordering == Ordering::Less,
+            Operator::LessEqual => ordering != Ordering::Greater,
+            Operator::Greater => ordering == Ordering::Greater,
+            Operator::GreaterEqual => ordering != Ordering::Less
+        }
+    }
+}
+
+/// Properties of an entry which can be compared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field
+{
+    Name,
+    Path,
+    Ext,
+    Type,
+    User,
+    Group,
+    Size,
+    Links,
+    Time(TimeField)
+}
+
+impl Field
+{
+    /// Look up a field by the name used in expressions
+    fn parse(name: &str) -> Option<Field>
+    {
+        match name
+        {
+            "name" => Some(Field::Name),
+            "path" => Some(Field::Path),
+            "ext" => Some(Field::Ext),
+            "type" => Some(Field::Type),
+            "user" => Some(Field::User),
+            "group" => Some(Field::Group),
+            "size" => Some(Field::Size),
+            "links" => Some(Field::Links),
+            "mtime" => Some(Field::Time(TimeField::Modified)),
+            "atime" => Some(Field::Time(TimeField::Accessed)),
+            "ctime" => Some(Field::Time(TimeField::Changed)),
+            "btime" => Some(Field::Time(TimeField::Created)),
+            _ => None
+        }
+    }
+
+    /// Returns true if the field holds text, which can be matched against
+    /// regular expressions but not ordered
+    fn is_text(self) -> bool
+    {
+        matches!(self, Field::Name | Field::Path | Field::Ext | Field::User | Field::Group)
+    }
+}
+
+/// A value to compare a field against, checked against the field when parsed
+#[derive(Debug, Clone)]
+pub enum Value
+{
+    /// Text compared exactly
+    Text(String),
+    /// Regular expression
+    Pattern(Regex),
+    /// Letter of a kind of entry, as given to --type
+    Type(char),
+    /// Size in bytes or number of links
+    Number(u64),
+    /// Point in time a number of seconds ago, compared the same as a time so
+    /// that `mtime < 7d` means changed before 7 days ago
+    Age(i64),
+    /// Point in time in seconds since the epoch
+    Time(i64)
+}
+
+/// A parsed expression
+#[derive(Debug, Clone)]
+pub enum Expr
+{
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Compare a field against a value
+    Compare(Field, Operator, Value),
+    /// Check whether a field is equal to any of the values
+    In(Field, Vec<Value>)
+}
+
+/// Split an expression into tokens, each with the range of bytes it covers
+fn tokenize(source: &str) -> Result<Vec<(Token, usize, usize)>, String>
+{
+    let mut tokens = vec![];
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let mut i = 0;
+
+    while i < chars.len()
+    {
+        let (start, c) = chars[i];
+
+        if c.is_whitespace()
+        {
+            i += 1;
+            continue;
+        }
+
+        // Operators and punctuation of one or two characters
+        let next = chars.get(i + 1).map(|(_, next)| *next);
+        let symbol = match (c, next)
+        {
+            ('(', _) => Some((Token::Open, 1)),
+            (')', _) => Some((Token::Close, 1)),
+            (',', _) => Some((Token::Comma, 1)),
+            ('=', Some('=')) => Some((Token::Operator(Operator::Equal), 2)),
+            ('=', _) => Some((Token::Operator(Operator::Equal), 1)),
+            ('!', Some('=')) => Some((Token::Operator(Operator::NotEqual), 2)),
+            ('!', Some('~')) => Some((Token::Operator(Operator::NotMatch), 2)),
+            ('<', Some('=')) => Some((Token::Operator(Operator::LessEqual), 2)),
+            ('<', _) => Some((Token::Operator(Operator::Less), 1)),
+            ('>', Some('=')) => Some((Token::Operator(Operator::GreaterEqual), 2)),
+            ('>', _) => Some((Token::Operator(Operator::Greater), 1)),
+            ('~', _) => Some((Token::Operator(Operator::Match), 1)),
+            _ => None
+        };
+
+        if let Some((token, length)) = symbol
+        {
+            let end = chars.get(i + length).map_or(source.len(), |(end, _)| *end);
+            tokens.push((token, start, end));
+            i += length;
+            continue;
+        }
+
+        if c == '"' || c == '\''
+        {
+            let mut text = String::from("");
+            i += 1;
+
+            loop
+            {
+                match chars.get(i)
+                {
+                    // Only quotes and backslashes are escaped, so regular
+                    // expressions can be written as they are
+                    Some((_, '\\')) if chars.get(i + 1).is_some_and(|(_, next)| *next == c || *next == '\\') =>
+                    {
+                        text.push(chars[i + 1].1);
+                        i += 2;
+                    },
+                    Some((_, quote)) if *quote == c =>
+                    {
+                        i += 1;
+                        break;
+                    },
+                    Some((_, other)) =>
+                    {
+                        text.push(*other);
+                        i += 1;
+                    },
+                    None => {return Err(error_at(source, start, source.len(), "unterminated string"));}
+                }
+            }
+
+            let end = chars.get(i).map_or(source.len(), |(end, _)| *end);
+            tokens.push((Token::Text(text), start, end));
+            continue;
+        }
+
+        if c == '!'
+        {
+            return Err(error_at(source, start, start + 1, "expected '!=' or '!~'"));
+        }
+
+        // Anything else runs until whitespace or punctuation
+        let mut word = String::from("");
+
+        while let Some((_, c)) = chars.get(i)
+        {
+            if c.is_whitespace() || "(),=!<>~\"'".contains(*c)
+            {
+                break;
+            }
+
+            word.push(*c);
+            i += 1;
+        }
+
+        let end = chars.get(i).map_or(source.len(), |(end, _)| *end);
+        tokens.push((Token::Word(word), start, end));
+    }
+
+    tokens.push((Token::End, source.len(), source.len()));
+
+    Ok(tokens)
+}
+
+/// Build an error message which shows the expression with the offending
+/// part underlined
+fn error_at(source: &str, start: usize, end: usize, message: &str) -> String
+{
+    let column = source[..start].chars().count();
+    let width = source[start..end].chars().count().max(1);
+
+    format!("Invalid expression: {}\n  {}\n  {}{}", message, source, " ".repeat(column), "^".repeat(width))
+}
+
+/// Recursive descent parser over the tokens of an expression
+struct Parser<'a>
+{
+    /// Expression being parsed
+    source: &'a str,
+    /// Tokens with their byte ranges
+    tokens: Vec<(Token, usize, usize)>,
+    /// Index of the next token
+    position: usize
+}
+
+impl<'a> Parser<'a>
+{
+    /// Look at the next token without consuming it
+    fn peek(&self) -> &Token
+    {
+        &self.tokens[self.position].0
+    }
+
+    /// Consume the next token
+    fn next(&mut self) -> (Token, usize, usize)
+    {
+        let token = self.tokens[self.position].clone();
+
+        if self.position < self.tokens.len() - 1
+        {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    /// Build an error pointing at the next token
+    fn error(&self, message: &str) -> String
+    {
+        let (_, start, end) = &self.tokens[self.position];
+        error_at(self.source, *start, *end, message)
+    }
+
+    /// Returns true and consumes the next token if it is the given keyword
+    fn keyword(&mut self, keyword: &str) -> bool
+    {
+        match self.peek()
+        {
+            Token::Word(word) if word.eq_ignore_ascii_case(keyword) =>
+            {
+                self.next();
+                true
+            },
+            _ => false
+        }
+    }
+
+    /// or := and ("or" and)*
+    fn parse_or(&mut self) -> Result<Expr, String>
+    {
+        let mut expr = self.parse_and()?;
+
+        while self.keyword("or")
+        {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        Ok(expr)
+    }
+
+    /// and := not ("and" not)*
+    fn parse_and(&mut self) -> Result<Expr, String>
+    {
+        let mut expr = self.parse_not()?;
+
+        while self.keyword("and")
+        {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+
+        Ok(expr)
+    }
+
+    /// not := "not" not | "(" or ")" | comparison
+    fn parse_not(&mut self) -> Result<Expr, String>
+    {
+        if self.keyword("not")
+        {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+
+        if *self.peek() == Token::Open
+        {
+            self.next();
+            let expr = self.parse_or()?;
+
+            if *self.peek() != Token::Close
+            {
+                return Err(self.error("expected ')'"));
+            }
+            self.next();
+
+            return Ok(expr);
+        }
+
+        self.parse_comparison()
+    }
+
+    /// comparison := field operator value | field "in" "(" value ("," value)* ")"
+    fn parse_comparison(&mut self) -> Result<Expr, String>
+    {
+        let field = match self.peek()
+        {
+            Token::Word(word) => match Field::parse(&word.to_lowercase())
+            {
+                Some(field) => field,
+                None => {return Err(self.error("unknown field, expected name, path, ext, type, user, group, size, links, \
+                                                mtime, atime, ctime or btime"));}
+            },
+            Token::End => {return Err(self.error("expected a comparison"));},
+            _ => {return Err(self.error("expected a field name"));}
+        };
+        self.next();
+
+        if self.keyword("in")
+        {
+            if *self.peek() != Token::Open
+            {
+                return Err(self.error("expected '(' after 'in'"));
+            }
+            self.next();
+
+            let mut values = vec![self.parse_value(field, Operator::Equal)?];
+
+            while *self.peek() == Token::Comma
+            {
+                self.next();
+                values.push(self.parse_value(field, Operator::Equal)?);
+            }
+
+            if *self.peek() != Token::Close
+            {
+                return Err(self.error("expected ',' or ')'"));
+            }
+            self.next();
+
+            return Ok(Expr::In(field, values));
+        }
+
+        let operator = match self.peek()
+        {
+            Token::Operator(operator) => *operator,
+            _ => {return Err(self.error("expected an operator such as =, !=, <, >, ~ or in"));}
+        };
+
+        let is_ordering = matches!(operator, Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual);
+        let is_matching = matches!(operator, Operator::Match | Operator::NotMatch);
+
+        if is_ordering && (field.is_text() || field == Field::Type)
+        {
+            return Err(self.error("this field can't be ordered, only compared with =, != or ~"));
+        }
+
+        if is_matching && !field.is_text()
+        {
+            return Err(self.error("only text fields can be matched with ~"));
+        }
+        self.next();
+
+        let value = self.parse_value(field, operator)?;
+
+        Ok(Expr::Compare(field, operator, value))
+    }
+
+    /// Parse a value and check it makes sense for the field it is compared to
+    fn parse_value(&mut self, field: Field, operator: Operator) -> Result<Value, String>
+    {
+        let text = match self.peek()
+        {
+            Token::Word(word) if KEYWORDS.contains(&word.to_lowercase().as_str()) => {return Err(self.error("expected a value"));},
+            Token::Word(word) | Token::Text(word) => word.clone(),
+            _ => {return Err(self.error("expected a value"));}
+        };
+
+        let value = match field
+        {
+            _ if matches!(operator, Operator::Match | Operator::NotMatch) => match Regex::new(&text)
+            {
+                Ok(regex) => Value::Pattern(regex),
+                Err(_) => {return Err(self.error("invalid regular expression"));}
+            },
+            Field::Type => match text.as_str()
+            {
+                "f" | "file" => Value::Type('f'),
+                "d" | "dir" | "directory" => Value::Type('d'),
+                "l" | "link" | "symlink" => Value::Type('l'),
+                "p" | "pipe" | "fifo" => Value::Type('p'),
+                "s" | "socket" => Value::Type('s'),
+                "b" | "block" => Value::Type('b'),
+                "c" | "char" => Value::Type('c'),
+                "x" | "exec" | "executable" => Value::Type('x'),
+                _ => {return Err(self.error("unknown type, expected f, d, l, p, s, b, c or x"));}
+            },
+            Field::Size => match parse_size(&text)
+            {
+                Ok(size) => Value::Number(size),
+                Err(_) => {return Err(self.error("invalid size, expected a number such as 512, 10K or 1.5MB"));}
+            },
+            Field::Links => match text.parse()
+            {
+                Ok(links) => Value::Number(links),
+                Err(_) => {return Err(self.error("expected a number of links"));}
+            },
+            Field::Time(time_field) =>
+            {
+                if let Ok(age) = parse_duration(&text)
+                {
+                    Value::Age(age)
+                }
+                else
+                {
+                    match filter::parse_when(&text, time_field)
+                    {
+                        Ok(time) => Value::Time(time),
+                        Err(_) => {return Err(self.error("invalid time, expected an age such as 7d, a date such as \
+                                                          \"2024-01-31 12:00\" or a file"));}
+                    }
+                }
+            },
+            _ => Value::Text(text)
+        };
+        self.next();
+
+        Ok(value)
+    }
+}
+
+/// Parse an expression, such as `size > 10M and ext in (log, gz)`
+pub fn parse(source: &str) -> Result<Expr, String>
+{
+    let mut parser = Parser
+    {
+        source,
+        tokens: tokenize(source)?,
+        position: 0
+    };
+
+    let expr = parser.parse_or()?;
+
+    if *parser.peek() != Token::End
+    {
+        return Err(parser.error("expected 'and', 'or' or the end of the expression"));
+    }
+
+    Ok(expr)
+}
+
+/// Get the text of a text field of an entry
+fn text_of(field: Field, path: &Path, metadata: &std::fs::Metadata) -> String
+{
+    let lossy = |value: Option<&std::ffi::OsStr>| value.map(|value| value.to_string_lossy().into_owned()).unwrap_or_default();
+
+    match field
+    {
+        Field::Name => lossy(path.file_name()),
+        Field::Path => path.to_string_lossy().into_owned(),
+        Field::Ext => lossy(path.extension()),
+        Field::User => match users::get_user_by_uid(metadata.uid())
+        {
+            Some(user) => user.name().to_string_lossy().into_owned(),
+            None => metadata.uid().to_string()
+        },
+        Field::Group => match users::get_group_by_gid(metadata.gid())
+        {
+            Some(group) => group.name().to_string_lossy().into_owned(),
+            None => metadata.gid().to_string()
+        },
+        _ => String::from("")
+    }
+}
+
+/// Compare a field of an entry against a single value
+fn compare(field: Field, operator: Operator, value: &Value, path: &Path, metadata: &std::fs::Metadata) -> bool
+{
+    match value
+    {
+        Value::Text(text) => operator.accepts(text_of(field, path, metadata).as_str().cmp(text.as_str())),
+        Value::Pattern(regex) =>
+        {
+            let ordering = if regex.is_match(&text_of(field, path, metadata)) {Ordering::Equal} else {Ordering::Less};
+            operator.accepts(ordering)
+        },
+        Value::Type(letter) =>
+        {
+            let ordering = if filter::is_type(metadata, *letter) {Ordering::Equal} else {Ordering::Less};
+            operator.accepts(ordering)
+        },
+        Value::Number(number) =>
+        {
+            let actual = if field == Field::Links {metadata.nlink()} else {metadata.len()};
+            operator.accepts(actual.cmp(number))
+        },
+        Value::Age(age) =>
+        {
+            let time = if let Field::Time(time_field) = field {time_field.of(metadata)} else {0};
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|since_epoch| since_epoch.as_secs() as i64).unwrap_or(0);
+            operator.accepts(time.cmp(&(now - age)))
+        },
+        Value::Time(limit) =>
+        {
+            let time = if let Field::Time(time_field) = field {time_field.of(metadata)} else {0};
+            operator.accepts(time.cmp(limit))
+        }
+    }
+}
+
+impl Expr
+{
+    /// Returns true if the entry at the given path satisfies the expression
+    pub fn matches(&self, path: &Path, metadata: &std::fs::Metadata) -> bool
+    {
+        match self
+        {
+            Expr::And(left, right) => left.matches(path, metadata) && right.matches(path, metadata),
+            Expr::Or(left, right) => left.matches(path, metadata) || right.matches(path, metadata),
+            Expr::Not(expr) => !expr.matches(path, metadata),
+            Expr::Compare(field, operator, value) => compare(*field, *operator, value, path, metadata),
+            Expr::In(field, values) => values.iter().any(|value| compare(*field, Operator::Equal, value, path, metadata))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Describe the structure of an expression, with every operation in
+    /// parentheses
+    fn describe(expr: &Expr) -> String
+    {
+        let value = |value: &Value| match value
+        {
+            Value::Text(text) => format!("{:?}", text),
+            Value::Pattern(regex) => format!("/{}/", regex.as_str()),
+            Value::Type(letter) => format!("type {}", letter),
+            Value::Number(number) => number.to_string(),
+            Value::Age(age) => format!("{}s ago", age),
+            Value::Time(time) => format!("@{}", time)
+        };
+
+        match expr
+        {
+            Expr::And(left, right) => format!("({} and {})", describe(left), describe(right)),
+            Expr::Or(left, right) => format!("({} or {})", describe(left), describe(right)),
+            Expr::Not(expr) => format!("(not {})", describe(expr)),
+            Expr::Compare(field, operator, compared) => format!("{:?} {:?} {}", field, operator, value(compared)),
+            Expr::In(field, values) => format!("{:?} in [{}]", field, values.iter().map(value).collect::<Vec<String>>().join(", "))
+        }
+    }
+
+    /// Parse an expression which is expected to be valid and describe it
+    fn parsed(source: &str) -> String
+    {
+        match parse(source)
+        {
+            Ok(expr) => describe(&expr),
+            Err(e) => panic!("parsing '{}' failed with {}", source, e)
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or()
+    {
+        let cases = [("name = a or name = b and name = c", "(Name Equal \"a\" or (Name Equal \"b\" and Name Equal \"c\"))"),
+                     ("name = a and name = b or name = c", "((Name Equal \"a\" and Name Equal \"b\") or Name Equal \"c\")"),
+                     ("not name = a and name = b", "((not Name Equal \"a\") and Name Equal \"b\")"),
+                     ("not not name = a", "(not (not Name Equal \"a\"))"),
+                     ("name = a or name = b or name = c", "((Name Equal \"a\" or Name Equal \"b\") or Name Equal \"c\")"),
+                     ("NOT name = a AND name = b Or name = c", "(((not Name Equal \"a\") and Name Equal \"b\") or Name Equal \"c\")")];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parsed(source), *expected, "parsing '{}'", source);
+        }
+    }
+
+    #[test]
+    fn parentheses_override_precedence()
+    {
+        let cases = [("(name = a or name = b) and name = c", "((Name Equal \"a\" or Name Equal \"b\") and Name Equal \"c\")"),
+                     ("name = a and (name = b or name = c)", "(Name Equal \"a\" and (Name Equal \"b\" or Name Equal \"c\"))"),
+                     ("not (name = a or name = b)", "(not (Name Equal \"a\" or Name Equal \"b\"))"),
+                     ("((name = a))", "Name Equal \"a\""),
+                     ("(name=a)and(name=b)", "(Name Equal \"a\" and Name Equal \"b\")")];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parsed(source), *expected, "parsing '{}'", source);
+        }
+    }
+
+    #[test]
+    fn parses_each_operator()
+    {
+        let cases = [("links = 1", "Links Equal 1"),
+                     ("links == 1", "Links Equal 1"),
+                     ("links != 1", "Links NotEqual 1"),
+                     ("links < 1", "Links Less 1"),
+                     ("links <= 1", "Links LessEqual 1"),
+                     ("links > 1", "Links Greater 1"),
+                     ("links >= 1", "Links GreaterEqual 1"),
+                     ("name ~ '^a.*'", "Name Match /^a.*/"),
+                     ("name !~ '\\.rs$'", "Name NotMatch /\\.rs$/"),
+                     ("ext in (log, 'gz', \"tar\")", "Ext in [\"log\", \"gz\", \"tar\"]")];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parsed(source), *expected, "parsing '{}'", source);
+        }
+    }
+
+    #[test]
+    fn parses_each_field()
+    {
+        let cases = [("name = a", "Name Equal \"a\""),
+                     ("path = 'src/ls.rs'", "Path Equal \"src/ls.rs\""),
+                     ("ext = rs", "Ext Equal \"rs\""),
+                     ("user = root", "User Equal \"root\""),
+                     ("group = wheel", "Group Equal \"wheel\""),
+                     ("type = f", "Type Equal type f"),
+                     ("type = directory", "Type Equal type d"),
+                     ("type in (link, exec)", "Type in [type l, type x]"),
+                     ("size = 0", "Size Equal 0"),
+                     ("links > 2", "Links Greater 2"),
+                     ("mtime < 1d", "Time(Modified) Less 86400s ago"),
+                     ("atime < 1d", "Time(Accessed) Less 86400s ago"),
+                     ("ctime < 1d", "Time(Changed) Less 86400s ago"),
+                     ("btime < 1d", "Time(Created) Less 86400s ago"),
+                     ("SIZE = 0", "Size Equal 0"),
+                     ("name = 'it\\'s'", "Name Equal \"it's\""),
+                     ("name = 'and'", "Name Equal \"and\"")];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parsed(source), *expected, "parsing '{}'", source);
+        }
+
+        assert!(parsed("mtime > '2024-01-31 12:00'").starts_with("Time(Modified) Greater @"));
+    }
+
+    #[test]
+    fn parses_size_literals()
+    {
+        let cases = [("size > 512", "Size Greater 512"),
+                     ("size > 10K", "Size Greater 10240"),
+                     ("size > 1.5M", "Size Greater 1572864"),
+                     ("size > 10KB", "Size Greater 10000"),
+                     ("size > 2GiB", "Size Greater 2147483648"),
+                     ("size > '1.5MB'", "Size Greater 1500000")];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parsed(source), *expected, "parsing '{}'", source);
+        }
+    }
+
+    #[test]
+    fn parses_duration_literals()
+    {
+        let cases = [("mtime < 90s", "Time(Modified) Less 90s ago"),
+                     ("mtime < 5m", "Time(Modified) Less 300s ago"),
+                     ("mtime < 2h", "Time(Modified) Less 7200s ago"),
+                     ("mtime < 1w", "Time(Modified) Less 604800s ago"),
+                     ("mtime < 1y", "Time(Modified) Less 31536000s ago"),
+                     ("mtime < 1h30m", "Time(Modified) Less 5400s ago")];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parsed(source), *expected, "parsing '{}'", source);
+        }
+    }
+
+    #[test]
+    fn matches_entries()
+    {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let metadata = std::fs::metadata(&path).unwrap();
+
+        let cases = [("name = Cargo.toml", true),
+                     ("name != Cargo.toml", false),
+                     ("name ~ '^cargo'", false),
+                     ("name ~ '(?i)^cargo'", true),
+                     ("name !~ '^Cargo'", false),
+                     ("ext in (rs, toml)", true),
+                     ("ext in (rs, lock)", false),
+                     ("type = f and not type = d", true),
+                     ("type = l", false),
+                     ("size > 0 and size < 1M", true),
+                     ("links >= 1", true),
+                     ("mtime > 1000y", true),
+                     ("mtime < 1000y", false),
+                     ("mtime > '1970-01-02'", true),
+                     ("name = a or ext = toml", true),
+                     ("name = a and ext = toml", false)];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parse(source).unwrap().matches(&path, &metadata), *expected, "matching '{}'", source);
+        }
+    }
+
+    #[test]
+    fn ages_and_dates_compare_the_same_way()
+    {
+        let path = std::env::temp_dir().join(format!("custom_ls_where_time_{}", std::process::id()));
+        let ten_days_ago = SystemTime::now() - std::time::Duration::from_secs(10 * 86400);
+        std::fs::File::create(&path).unwrap().set_modified(ten_days_ago).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+
+        let week_ago = chrono::Local::now() - chrono::Duration::days(7);
+        let date = week_ago.format("%Y-%m-%d %H:%M").to_string();
+
+        // Both mean changed before a week ago, which the file was
+        let cases = [("mtime < 7d".to_string(), true),
+                     (format!("mtime < '{}'", date), true),
+                     ("mtime > 7d".to_string(), false),
+                     (format!("mtime > '{}'", date), false),
+                     ("mtime > 11d".to_string(), true),
+                     ("mtime < 11d".to_string(), false)];
+
+        for (source, expected) in &cases
+        {
+            assert_eq!(parse(source).unwrap().matches(&path, &metadata), *expected, "matching '{}'", source);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn errors_point_at_the_bad_token()
+    {
+        let unknown_field = "unknown field, expected name, path, ext, type, user, group, size, links, mtime, atime, ctime or btime";
+        let trailing = "expected 'and', 'or' or the end of the expression";
+
+        let cases = [("nme = a", unknown_field, "^^^"),
+                     ("size > 1 and colour = red", unknown_field, "             ^^^^^^"),
+                     ("name = 'abc", "unterminated string", "       ^^^^"),
+                     ("name = a or path = \"x", "unterminated string", "                   ^^"),
+                     ("name = a b", trailing, "         ^"),
+                     ("name = a)", trailing, "        ^"),
+                     ("name = 'é' extra", trailing, "           ^^^^^"),
+                     ("(name = a", "expected ')'", "         ^"),
+                     ("size >", "expected a value", "      ^"),
+                     ("name < a", "this field can't be ordered, only compared with =, != or ~", "     ^"),
+                     ("size ~ a", "only text fields can be matched with ~", "     ^"),
+                     ("type = q", "unknown type, expected f, d, l, p, s, b, c or x", "       ^"),
+                     ("size > huge", "invalid size, expected a number such as 512, 10K or 1.5MB", "       ^^^^"),
+                     ("name ! a", "expected '!=' or '!~'", "     ^")];
+
+        for (source, message, carets) in &cases
+        {
+            let expected = format!("Invalid expression: {}\n  {}\n  {}", message, source, carets);
+            assert_eq!(parse(source).err(), Some(expected), "parsing '{}'", source);
+        }
+    }
+}
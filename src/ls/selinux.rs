@@ -0,0 +1,25 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use super::xattr;
+
+/// Attribute holding the SELinux security context of a file
+const CONTEXT_ATTRIBUTE: &str = "security.selinux";
+
+/// Read the SELinux security context of a file, such as
+/// `system_u:object_r:etc_t:s0`. Returns None if the file has no context,
+/// which is the case on systems without SELinux.
+pub fn read_context(path: &Path, follow: bool) -> Option<String>
+{
+    let value = xattr::get(path, OsStr::new(CONTEXT_ATTRIBUTE), follow).ok()?;
+
+    // The context is stored with a terminating null
+    let context = value.strip_suffix(&[0]).unwrap_or(&value);
+
+    if context.is_empty()
+    {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(context).into_owned())
+}
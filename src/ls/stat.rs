@@ -0,0 +1,323 @@
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use chrono::TimeZone;
+use colored::*;
+
+use super::{explain, json, read_metadata, Flags};
+
+/// Everything known about a single path, as shown by --stat
+#[derive(Debug)]
+pub struct Stat
+{
+    /// Path as given
+    path: String,
+    /// Target of the symbolic link, if the path is a link which is not followed
+    link_target: Option<String>,
+    /// Kind of file
+    kind: &'static str,
+    /// Apparent size in bytes
+    size: u64,
+    /// Number of 512 byte blocks allocated
+    blocks: u64,
+    /// Preferred size for I/O
+    block_size: u64,
+    /// Device the file is on
+    device: u64,
+    /// Device the file represents, for block and character devices
+    rdev: Option<u64>,
+    /// Inode number
+    inode: u64,
+    /// Number of hard links
+    links: u64,
+    /// File type and permission bits
+    mode: u32,
+    /// Owner id and name
+    uid: (u32, Option<String>),
+    /// Group id and name
+    gid: (u32, Option<String>),
+    /// Access, modification, status change and birth times as seconds and
+    /// nanoseconds since the epoch
+    times: [(&'static str, Option<(i64, u32)>); 4],
+    /// Mount point of the filesystem the file is on
+    mount_point: Option<String>
+}
+
+/// Split a device number into its major and minor numbers
+fn split_device(device: u64) -> (u64, u64)
+{
+    let major = ((device >> 8) & 0xfff) | ((device >> 32) & !0xfff);
+    let minor = (device & 0xff) | ((device >> 12) & !0xff);
+
+    (major, minor)
+}
+
+/// Kind of file, as described by stat
+fn kind_of(mode: u32) -> &'static str
+{
+    match mode & libc::S_IFMT
+    {
+        libc::S_IFDIR => "directory",
+        libc::S_IFLNK => "symbolic link",
+        libc::S_IFIFO => "fifo",
+        libc::S_IFSOCK => "socket",
+        libc::S_IFBLK => "block special file",
+        libc::S_IFCHR => "character special file",
+        _ => "regular file"
+    }
+}
+
+/// Undo the octal escapes used for spaces and other characters in
+/// /proc/self/mounts
+fn unescape_mount(field: &str) -> String
+{
+    let bytes = field.as_bytes();
+    let mut unescaped = vec![];
+    let mut i = 0;
+
+    while i < bytes.len()
+    {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|byte| (b'0'..=b'7').contains(byte))
+        {
+            unescaped.push((bytes[i + 1] - b'0') * 64 + (bytes[i + 2] - b'0') * 8 + (bytes[i + 3] - b'0'));
+            i += 4;
+        }
+        else
+        {
+            unescaped.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Find the mount point of the filesystem a path is on, which is the longest
+/// mount point containing it. Unless `follow` is set only the parent directory
+/// is resolved, so a symbolic link is on the filesystem it is in and not the
+/// one of its target.
+fn mount_point(path: &Path, follow: bool) -> Option<String>
+{
+    let path = match (follow, path.parent(), path.file_name())
+    {
+        (false, Some(parent), Some(name)) =>
+        {
+            let parent = if parent.as_os_str().is_empty() {Path::new(".")} else {parent};
+            std::fs::canonicalize(parent).ok()?.join(name)
+        },
+        _ => std::fs::canonicalize(path).ok()?
+    };
+    let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+
+    mounts.lines()
+          .filter_map(|line| line.split_whitespace().nth(1))
+          .map(|field| PathBuf::from(unescape_mount(field)))
+          .filter(|mount| path.starts_with(mount))
+          .max_by_key(|mount| mount.components().count())
+          .map(|mount| mount.to_string_lossy().into_owned())
+}
+
+/// Time since the epoch of a birth time, which not every filesystem records
+fn birth_time(metadata: &std::fs::Metadata) -> Option<(i64, u32)>
+{
+    let since_epoch = metadata.created().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    Some((since_epoch.as_secs() as i64, since_epoch.subsec_nanos()))
+}
+
+impl Stat
+{
+    /// Gather everything about a path, following a symbolic link if `follow`
+    /// is set
+    pub fn read(path_str: &str, follow: bool) -> Result<Stat, String>
+    {
+        let path = Path::new(path_str);
+
+        let metadata = match read_metadata(path, follow)
+        {
+            Ok(meta) => meta,
+            Err(_) => {return Err(format!("Path '{}' does not exist", path_str));}
+        };
+
+        let mode = metadata.permissions().mode();
+        let link_target = if metadata.file_type().is_symlink()
+        {
+            std::fs::read_link(path).ok().map(|target| target.to_string_lossy().into_owned())
+        }
+        else
+        {
+            None
+        };
+        let is_device = matches!(mode & libc::S_IFMT, libc::S_IFBLK | libc::S_IFCHR);
+
+        Ok(Stat
+        {
+            path: String::from(path_str),
+            link_target,
+            kind: kind_of(mode),
+            size: metadata.len(),
+            blocks: metadata.blocks(),
+            block_size: metadata.blksize(),
+            device: metadata.dev(),
+            rdev: if is_device {Some(metadata.rdev())} else {None},
+            inode: metadata.ino(),
+            links: metadata.nlink(),
+            mode,
+            uid: (metadata.uid(), users::get_user_by_uid(metadata.uid()).map(|user| user.name().to_string_lossy().into_owned())),
+            gid: (metadata.gid(), users::get_group_by_gid(metadata.gid()).map(|group| group.name().to_string_lossy().into_owned())),
+            times: [("access", Some((metadata.atime(), metadata.atime_nsec() as u32))),
+                    ("modify", Some((metadata.mtime(), metadata.mtime_nsec() as u32))),
+                    ("change", Some((metadata.ctime(), metadata.ctime_nsec() as u32))),
+                    ("birth", birth_time(&metadata))],
+            mount_point: mount_point(path, follow)
+        })
+    }
+
+    /// Render the block of lines shown for the path
+    pub fn render(&self) -> Vec<String>
+    {
+        let mut lines = vec![];
+
+        let (major, minor) = split_device(self.device);
+        let name = |id: &(u32, Option<String>)| id.1.clone().unwrap_or_else(|| String::from("unknown"));
+
+        lines.push(match &self.link_target
+        {
+            Some(target) => format!("  File: {} -> {}", self.path.bold(), target.bright_cyan()),
+            None => format!("  File: {}", self.path.bold())
+        });
+        lines.push(format!("  Type: {}", self.kind));
+        lines.push(format!("  Size: {}  Blocks: {}  IO Block: {}",
+                           format!("{:<12}", self.size).bright_green(),
+                           format!("{:<8}", self.blocks).bright_blue(),
+                           self.block_size));
+        lines.push(format!("Device: {:<12}  Inode: {}  Links: {}",
+                           format!("{},{}", major, minor),
+                           format!("{:<10}", self.inode).bright_purple(),
+                           self.links.to_string().bright_red()));
+
+        if let Some(rdev) = self.rdev
+        {
+            let (major, minor) = split_device(rdev);
+            lines.push(format!("  Dev.: {},{}", major, minor));
+        }
+
+        lines.push(format!("  Mode: {}  Octal: {}",
+                           explain::render_symbolic(self.mode).bold(),
+                           format!("{:04o}", self.mode & 0o7777).bright_blue()));
+        lines.push(format!("   Uid: {}  Gid: {}",
+                           format!("{:<20}", format!("{} ({})", self.uid.0, name(&self.uid))).bright_yellow(),
+                           format!("{} ({})", self.gid.0, name(&self.gid)).bright_yellow()));
+
+        for (label, time) in &self.times
+        {
+            // Times too far out to be represented are shown as seconds
+            let rendered = match time.map(|(secs, nanos)| (secs, chrono::Local.timestamp_opt(secs, nanos).single()))
+            {
+                Some((_, Some(local))) => format!("{}", local.format("%Y-%m-%d %H:%M:%S%.9f %z")),
+                Some((secs, None)) => secs.to_string(),
+                None => String::from("-")
+            };
+
+            lines.push(format!("{:>6}: {}", capitalize(label), rendered.bright_blue()));
+        }
+
+        lines.push(format!(" Mount: {}", self.mount_point.clone().unwrap_or_else(|| String::from("-"))));
+
+        lines
+    }
+
+    /// Render the fields as a JSON object
+    pub fn render_json(&self) -> String
+    {
+        let (major, minor) = split_device(self.device);
+        let optional_string = |value: &Option<String>| match value
+        {
+            Some(value) => json::string(value),
+            None => String::from("null")
+        };
+
+        let mut fields = vec![
+            format!("\"path\": {}", json::string(&self.path)),
+            format!("\"link_target\": {}", optional_string(&self.link_target)),
+            format!("\"type\": {}", json::string(self.kind)),
+            format!("\"size\": {}", self.size),
+            format!("\"blocks\": {}", self.blocks),
+            format!("\"block_size\": {}", self.block_size),
+            format!("\"device\": {{\"major\": {}, \"minor\": {}}}", major, minor),
+            format!("\"inode\": {}", self.inode),
+            format!("\"links\": {}", self.links),
+            format!("\"mode\": {{\"symbolic\": {}, \"octal\": \"{:04o}\", \"raw\": {}}}",
+                    json::string(&explain::render_symbolic(self.mode)), self.mode & 0o7777, self.mode),
+            format!("\"uid\": {}, \"user\": {}", self.uid.0, optional_string(&self.uid.1)),
+            format!("\"gid\": {}, \"group\": {}", self.gid.0, optional_string(&self.gid.1))];
+
+        if let Some(rdev) = self.rdev
+        {
+            let (major, minor) = split_device(rdev);
+            fields.push(format!("\"rdev\": {{\"major\": {}, \"minor\": {}}}", major, minor));
+        }
+
+        for (label, time) in &self.times
+        {
+            fields.push(match time
+            {
+                Some((secs, nanos)) => format!("\"{}\": {{\"sec\": {}, \"nsec\": {}}}", label, secs, nanos),
+                None => format!("\"{}\": null", label)
+            });
+        }
+
+        fields.push(format!("\"mount_point\": {}", optional_string(&self.mount_point)));
+
+        format!("  {{{}}}", fields.join(", "))
+    }
+}
+
+/// Capitalize the first letter of a label
+fn capitalize(label: &str) -> String
+{
+    let mut chars = label.chars();
+
+    match chars.next()
+    {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::from("")
+    }
+}
+
+/// Print the details of each path given on the command line, as blocks of
+/// text or as a JSON array
+pub fn print_stats(flags: &Flags) -> Result<(), String>
+{
+    let follow = flags.dereference || flags.dereference_command_line;
+    let mut stats = vec![];
+
+    for path in &flags.files
+    {
+        stats.push(Stat::read(path, follow)?);
+    }
+
+    if flags.stat_json
+    {
+        let objects: Vec<String> = stats.iter().map(|stat| stat.render_json()).collect();
+        println!("[\n{}\n]", objects.join(",\n"));
+
+        return Ok(());
+    }
+
+    for (i, stat) in stats.iter().enumerate()
+    {
+        if i > 0
+        {
+            println!();
+        }
+
+        for line in stat.render()
+        {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use colored::*;
+
+use super::{read_metadata, render_file_size, Display, Flags};
+
+/// Counts and sizes of the entries in a listing
+#[derive(Debug, Default)]
+pub struct Summary
+{
+    /// Number of regular files
+    files: usize,
+    /// Number of directories
+    directories: usize,
+    /// Number of symbolic links
+    symlinks: usize,
+    /// Number of pipes, sockets and devices
+    others: usize,
+    /// Total apparent size in bytes
+    size: u64,
+    /// Total number of 512 byte blocks allocated
+    blocks: u64,
+    /// Number of entries not listed because they are hidden
+    hidden: usize,
+    /// Number of entries not listed because of a filter
+    filtered: usize
+}
+
+impl Summary
+{
+    /// Count a listed entry
+    fn add(&mut self, metadata: &std::fs::Metadata)
+    {
+        let file_type = metadata.file_type();
+
+        if file_type.is_dir()
+        {
+            self.directories += 1;
+        }
+        else if file_type.is_symlink()
+        {
+            self.symlinks += 1;
+        }
+        else if file_type.is_file()
+        {
+            self.files += 1;
+        }
+        else
+        {
+            self.others += 1;
+        }
+
+        self.size += metadata.len();
+        self.blocks += metadata.blocks();
+    }
+
+    /// Add the counts from another summary to this one
+    fn merge(&mut self, other: &Summary)
+    {
+        self.files += other.files;
+        self.directories += other.directories;
+        self.symlinks += other.symlinks;
+        self.others += other.others;
+        self.size += other.size;
+        self.blocks += other.blocks;
+        self.hidden += other.hidden;
+        self.filtered += other.filtered;
+    }
+
+    /// Render the summary on a single line, the total is given in 1024 byte
+    /// blocks the same as the `total` line of GNU ls
+    fn render(&self, flags: &Flags) -> Result<String, String>
+    {
+        Ok(format!("{} files, {} directories, {} links, {} other, size {}, total {}, {} hidden, {} filtered",
+                   self.files.to_string().bright_green(),
+                   self.directories.to_string().bright_blue(),
+                   self.symlinks.to_string().bright_cyan(),
+                   self.others,
+                   render_file_size(self.size, flags)?.bright_green(),
+                   (self.blocks / 2).to_string().bright_blue(),
+                   self.hidden,
+                   self.filtered))
+    }
+}
+
+/// Print a summary of each directory when recursing, followed by a summary of
+/// the whole listing
+pub fn print_summary(display: &Display, flags: &Flags) -> Result<(), String>
+{
+    let mut directories: BTreeMap<String, Summary> = BTreeMap::new();
+
+    let files = display.files.iter().map(|file| (&file.name, &file.path_str, file.link_target.is_none()));
+    let dirs = display.directories.iter()
+                                  .filter(|dir| !flags.files.contains(&dir.path_str))
+                                  .map(|dir| (&dir.name, &dir.path_str, true));
+
+    for (name, path_str, follow) in files.chain(dirs)
+    {
+        let path = Path::new(path_str);
+        let summary = directories.entry(parent_of(path)).or_default();
+
+        if name.starts_with('.') && !flags.all
+        {
+            summary.hidden += 1;
+            continue;
+        }
+
+        match read_metadata(path, follow)
+        {
+            Ok(meta) => summary.add(&meta),
+            Err(e) => {return Err(format!("{:?}", e));}
+        }
+    }
+
+    for (parent, count) in &display.filtered
+    {
+        directories.entry(parent.clone()).or_default().filtered += count;
+    }
+
+    let mut total = Summary::default();
+
+    for (parent, summary) in &directories
+    {
+        if flags.recursive
+        {
+            println!("{}: {}", parent.bold(), summary.render(flags)?);
+        }
+
+        total.merge(summary);
+    }
+
+    println!("{}: {}", "total".bold(), total.render(flags)?);
+
+    Ok(())
+}
+
+/// Get the directory containing an entry, as displayed in the summary
+pub fn parent_of(path: &Path) -> String
+{
+    match path.parent()
+    {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().into_owned(),
+        _ => String::from(".")
+    }
+}
@@ -0,0 +1,206 @@
+use std::ffi::{CString, OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+
+use colored::*;
+
+use super::acl;
+
+/// Maximum number of bytes of an attribute value to display
+const MAX_VALUE_LENGTH: usize = 64;
+
+/// Convert a path or attribute name into a C string
+fn to_cstring(value: &OsStr) -> std::io::Result<CString>
+{
+    match CString::new(value.as_bytes())
+    {
+        Ok(string) => Ok(string),
+        Err(_) => Err(std::io::Error::from_raw_os_error(libc::EINVAL))
+    }
+}
+
+/// List the names of the extended attributes of a file, only following a
+/// symbolic link if `follow` is set
+pub fn list(path: &Path, follow: bool) -> std::io::Result<Vec<OsString>>
+{
+    let c_path = to_cstring(path.as_os_str())?;
+
+    loop
+    {
+        // Safety: a null buffer of length zero asks only for the size needed
+        let size = unsafe
+        {
+            if follow
+            {
+                libc::listxattr(c_path.as_ptr(), std::ptr::null_mut(), 0)
+            }
+            else
+            {
+                libc::llistxattr(c_path.as_ptr(), std::ptr::null_mut(), 0)
+            }
+        };
+
+        if size < 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut buffer = vec![0u8; size as usize];
+
+        // Safety: the buffer is valid for writes of its whole length
+        let size = unsafe
+        {
+            if follow
+            {
+                libc::listxattr(c_path.as_ptr(), buffer.as_mut_ptr() as *mut libc::c_char, buffer.len())
+            }
+            else
+            {
+                libc::llistxattr(c_path.as_ptr(), buffer.as_mut_ptr() as *mut libc::c_char, buffer.len())
+            }
+        };
+
+        if size < 0
+        {
+            let error = std::io::Error::last_os_error();
+
+            // The attributes changed between the two calls, so try again
+            if error.raw_os_error() == Some(libc::ERANGE)
+            {
+                continue;
+            }
+
+            return Err(error);
+        }
+
+        buffer.truncate(size as usize);
+
+        return Ok(buffer.split(|byte| *byte == 0)
+                        .filter(|name| !name.is_empty())
+                        .map(|name| OsString::from_vec(name.to_vec()))
+                        .collect());
+    }
+}
+
+/// Get the value of an extended attribute of a file, only following a
+/// symbolic link if `follow` is set
+pub fn get(path: &Path, name: &OsStr, follow: bool) -> std::io::Result<Vec<u8>>
+{
+    let c_path = to_cstring(path.as_os_str())?;
+    let c_name = to_cstring(name)?;
+
+    loop
+    {
+        // Safety: a null buffer of length zero asks only for the size needed
+        let size = unsafe
+        {
+            if follow
+            {
+                libc::getxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0)
+            }
+            else
+            {
+                libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0)
+            }
+        };
+
+        if size < 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut buffer = vec![0u8; size as usize];
+
+        // Safety: the buffer is valid for writes of its whole length
+        let size = unsafe
+        {
+            if follow
+            {
+                libc::getxattr(c_path.as_ptr(), c_name.as_ptr(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+            }
+            else
+            {
+                libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+            }
+        };
+
+        if size < 0
+        {
+            let error = std::io::Error::last_os_error();
+
+            // The value changed between the two calls, so try again
+            if error.raw_os_error() == Some(libc::ERANGE)
+            {
+                continue;
+            }
+
+            return Err(error);
+        }
+
+        buffer.truncate(size as usize);
+
+        return Ok(buffer);
+    }
+}
+
+/// Returns true if a file has any extended attributes
+pub fn has_attributes(path: &Path, follow: bool) -> bool
+{
+    match list(path, follow)
+    {
+        Ok(names) => !names.is_empty(),
+        Err(_) => false
+    }
+}
+
+/// Render the value of an attribute as quoted text if it is printable, or as
+/// hex otherwise
+fn render_value(value: &[u8]) -> String
+{
+    let shown = &value[..value.len().min(MAX_VALUE_LENGTH)];
+
+    // Values are often stored with a terminating null
+    let text = shown.strip_suffix(&[0]).unwrap_or(shown);
+
+    let mut rendered = if !text.is_empty() && text.iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+    {
+        format!("\"{}\"", String::from_utf8_lossy(text))
+    }
+    else
+    {
+        let hex: Vec<String> = shown.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("0x{}", hex.join(""))
+    };
+
+    if value.len() > MAX_VALUE_LENGTH
+    {
+        rendered += &format!("... ({} bytes)", value.len());
+    }
+
+    rendered
+}
+
+/// Render a line for each extended attribute of a file, indented by `indent`
+/// spaces. The ACL attributes are left out if `skip_acls` is set, for when
+/// they are shown decoded instead.
+pub fn render_attributes(path: &Path, follow: bool, skip_acls: bool, indent: usize) -> Vec<String>
+{
+    let mut names = match list(path, follow)
+    {
+        Ok(names) => names,
+        Err(_) => {return vec![];}
+    };
+    names.retain(|name| !skip_acls || !acl::is_acl_attribute(name));
+    names.sort();
+
+    names.iter().map(|name|
+    {
+        let value = match get(path, name, follow)
+        {
+            Ok(value) => render_value(&value),
+            Err(_) => format!("{}", "<unreadable>".red())
+        };
+
+        format!("{:indent$}{} = {}", "", name.to_string_lossy().bright_purple(), value, indent = indent)
+    }).collect()
+}
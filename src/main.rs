@@ -2,6 +2,11 @@
 
 extern crate chrono;
 extern crate colored;
+extern crate git2;
+extern crate glob;
+extern crate libc;
+extern crate rayon;
+extern crate regex;
 extern crate users;
 
 mod ls;